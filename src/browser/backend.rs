@@ -0,0 +1,295 @@
+//! Choice between the native readers of this crate and the `rookie` crate.
+use std::{fmt, path::Path, str::FromStr};
+
+use super::chromium::Chromium;
+use super::cookie::SiteCookie;
+use super::firefox::Firefox;
+use super::firefox_profiles::FirefoxProfile;
+use super::profiles::{self, Family, ProfileInfo};
+use super::source::{self, Query};
+use super::spec::{BrowserSpec, Keyring};
+use super::utils::get_site;
+use crate::{Error, Result};
+
+#[cfg(feature = "rookie")]
+use super::rookie_utils as rookie;
+
+/// Stand-in for the rookie backend when the crate is built without the `rookie` feature.
+#[cfg(not(feature = "rookie"))]
+mod rookie {
+    use super::super::cookie::SiteCookie;
+    use crate::{Error, Result};
+    use std::path::Path;
+
+    fn unavailable() -> Error {
+        Error::Backend("gcookie was built without the rookie feature".into())
+    }
+
+    pub fn load_cookies(_browser: &str, _site: &str) -> Result<SiteCookie> {
+        Err(unavailable())
+    }
+
+    pub fn load_chrome_by_path(_site: &str, _path: &Path) -> Result<SiteCookie> {
+        Err(unavailable())
+    }
+
+    pub fn load_firefox_by_path(_site: &str, _path: &Path) -> Result<SiteCookie> {
+        Err(unavailable())
+    }
+}
+
+/// Implementation reading the cookie databases.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// The readers of this crate. Required for Firefox containers and Linux keyrings.
+    Native,
+    /// The `rookie` crate; only available with the `rookie` feature.
+    Rookie,
+    /// Native, falling back to rookie when the native reader fails, e.g. on the v20
+    /// app-bound cookies of Chrome on Windows.
+    #[default]
+    Auto,
+}
+
+impl Backend {
+    pub const NAMES: [&'static str; 3] = ["native", "rookie", "auto"];
+
+    fn read(
+        self,
+        native: impl FnOnce() -> Result<SiteCookie>,
+        rookie: impl FnOnce() -> Result<SiteCookie>,
+    ) -> Result<SiteCookie> {
+        match self {
+            Backend::Native => native(),
+            Backend::Rookie => rookie(),
+            // the native error is the one worth reporting
+            Backend::Auto => native().or_else(|err| rookie().map_err(|_| err)),
+        }
+    }
+
+    /// Cookies of the site in a Firefox profile directory.
+    pub fn load_firefox_by_path(self, site: &str, path: &Path) -> Result<SiteCookie> {
+        self.read(
+            || Firefox::new(path.to_path_buf()).get_site_cookies(&get_site(site)?),
+            || rookie::load_firefox_by_path(site, path),
+        )
+    }
+
+    /// Cookies of the site in a Chromium profile directory, with the key read from
+    /// `keyring` on Linux.
+    pub fn load_chrome_by_path(
+        self,
+        site: &str,
+        path: &Path,
+        keyring: Option<Keyring>,
+    ) -> Result<SiteCookie> {
+        self.read(
+            || native_chrome(site, path, keyring),
+            || {
+                let mut cookies = rookie::load_chrome_by_path(site, path)?;
+                if let Some(keyring) = keyring {
+                    cookies.push_warning(format!(
+                        "keyring {} is ignored: rookie picks the keyring itself",
+                        keyring
+                    ));
+                }
+                Ok(cookies)
+            },
+        )
+    }
+
+    /// Cookies of the site in the default profile of a browser.
+    ///
+    /// A [registered](source::register) source of that name, or the vault reader, is read
+    /// instead, whatever the backend.
+    pub fn load_cookies(self, browser: &str, site: &str) -> Result<SiteCookie> {
+        if let Some(source) = source::external(browser) {
+            return source.open(None)?.cookies(&Query::site(site)?);
+        }
+        self.read(
+            || match profiles::resolve_profile_path(browser, None)? {
+                (Family::Gecko, path) => Backend::Native.load_firefox_by_path(site, &path),
+                (Family::Chromium, path) => native_chrome(site, &path, None),
+            },
+            || rookie::load_cookies(browser, site),
+        )
+    }
+
+    /// Cookies of the site in the profile named by `spec`.
+    ///
+    /// Containers are only read by the native backend. A [registered](source::register)
+    /// source named like the browser, or the vault reader for `vault:/path`, is read
+    /// instead, with the profile of the spec.
+    pub fn load_cookies_by_spec(self, site: &str, spec: &BrowserSpec) -> Result<SiteCookie> {
+        if let Some(source) = source::external(&spec.browser) {
+            return source
+                .open(spec.profile_text().as_deref())?
+                .cookies(&Query::site(site)?);
+        }
+        match (&spec.container, spec.family()) {
+            (Some(_), _) if self == Backend::Rookie => Err(Error::Backend(
+                "containers are only read by the native backend".into(),
+            )),
+            (Some(container), _) => {
+                let (_, path) = spec.resolve()?;
+                let id = FirefoxProfile::from_path(&path).container_id(container)?;
+                Firefox::new(path)
+                    .in_container(id)
+                    .get_site_cookies(&get_site(site)?)
+            }
+            (None, Family::Gecko) => {
+                let (_, path) = spec.resolve()?;
+                self.load_firefox_by_path(site, &path)
+            }
+            (None, Family::Chromium) if spec.profile.is_none() && spec.keyring.is_none() => {
+                self.load_cookies(&spec.browser, site)
+            }
+            (None, Family::Chromium) => {
+                let (_, path) = spec.resolve()?;
+                self.load_chrome_by_path(site, &path, spec.keyring)
+            }
+        }
+    }
+
+    /// Try `specs` in order and return the first one that finds cookies for the site.
+    ///
+    /// When none finds any, the empty result of the first readable spec is returned, or
+    /// the first error when no spec could be read.
+    pub fn load_cookies_by_specs<'a>(
+        self,
+        site: &str,
+        specs: &'a [BrowserSpec],
+    ) -> Result<(&'a BrowserSpec, SiteCookie)> {
+        let mut empty = None;
+        let mut first_err = None;
+        for spec in specs {
+            match self.load_cookies_by_spec(site, spec) {
+                Ok(cookies) if !cookies.is_empty() => return Ok((spec, cookies)),
+                Ok(cookies) => {
+                    empty.get_or_insert((spec, cookies));
+                }
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        match (empty, first_err) {
+            (Some(found), _) => Ok(found),
+            (None, Some(err)) => Err(err),
+            (None, None) => Err(Error::NotFound("browser".into())),
+        }
+    }
+
+    /// Read the site from every detected profile and return the one whose cookies were
+    /// accessed most recently, together with that profile; no profile when none has cookies.
+    ///
+    /// Firefox cookies in containers are left out, except by the rookie backend. Profiles
+    /// that cannot be read are listed in the warnings of the result. On Windows, Chromium
+    /// profiles held by a running browser are skipped rather than closing it.
+    pub fn load_freshest_cookies(self, site: &str) -> Result<(Option<ProfileInfo>, SiteCookie)> {
+        let host = get_site(site)?;
+        let mut warnings = vec![];
+        let mut best: Option<(Option<i64>, ProfileInfo, SiteCookie)> = None;
+        for profile in profiles::list_profiles() {
+            if !profile.cookies_path.exists() {
+                continue;
+            }
+            if profile.locked && profile.family == Family::Chromium && cfg!(target_os = "windows") {
+                warnings.push(format!(
+                    "{} {}: skipped, the browser is running",
+                    profile.browser, profile.name
+                ));
+                continue;
+            }
+            let cookies = match profile.family {
+                Family::Gecko => self.read(
+                    || {
+                        Firefox::new(profile.path.clone())
+                            .in_container(0)
+                            .get_site_cookies(&host)
+                    },
+                    || rookie::load_firefox_by_path(site, &profile.path),
+                ),
+                Family::Chromium => self.load_chrome_by_path(site, &profile.path, None),
+            };
+            let cookies = match cookies {
+                Ok(cookies) if cookies.is_empty() => continue,
+                Ok(cookies) => cookies,
+                Err(err) => {
+                    warnings.push(format!("{} {}: {}", profile.browser, profile.name, err));
+                    continue;
+                }
+            };
+            let freshness = cookies
+                .iter()
+                .filter_map(|cookie| cookie.last_access.or(cookie.creation))
+                .max();
+            // the first profile wins ties, and any profile with timestamps beats one without
+            if best.as_ref().is_none_or(|(best, ..)| freshness > *best) {
+                best = Some((freshness, profile, cookies));
+            }
+        }
+        let (profile, mut cookies) = match best {
+            Some((_, profile, cookies)) => (Some(profile), cookies),
+            None => (None, SiteCookie::new()),
+        };
+        for warning in warnings {
+            cookies.push_warning(warning);
+        }
+        Ok((profile, cookies))
+    }
+}
+
+fn native_chrome(site: &str, path: &Path, keyring: Option<Keyring>) -> Result<SiteCookie> {
+    let chromium = Chromium::new(path.to_path_buf()).with_keyring(keyring);
+    #[cfg(target_os = "windows")]
+    if !chromium.is_v10()? {
+        return Err(Error::KeyUnavailable(
+            "v20 app-bound cookies are not read by the native backend".into(),
+        ));
+    }
+    chromium.get_site_cookies(&get_site(site)?)
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "native" => Ok(Backend::Native),
+            "rookie" => Ok(Backend::Rookie),
+            "auto" => Ok(Backend::Auto),
+            _ => Err(Error::Config(format!(
+                "unknown backend {}; please use {}",
+                s,
+                Backend::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::Native => "native",
+            Backend::Rookie => "rookie",
+            Backend::Auto => "auto",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_backend_ok() {
+        for name in Backend::NAMES {
+            assert_eq!(name.parse::<Backend>().unwrap().to_string(), name);
+        }
+        assert_eq!("Native".parse::<Backend>().unwrap(), Backend::Native);
+        assert_eq!(Backend::default(), Backend::Auto);
+        assert!(matches!("sqlite".parse::<Backend>(), Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,337 @@
+use rusqlite::{params, Connection, OptionalExtension, Params, Row};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use super::chromium_profiles::{browser_of, user_data_dir, ChromiumProfile};
+#[cfg(not(target_os = "windows"))]
+use super::chromium_unix::Keys;
+#[cfg(target_os = "windows")]
+use super::chromium_windows::Keys;
+use super::cookie::{Cookie, SameSite, SiteCookie};
+use super::import::{backup_database, invalid, now, Deleted, Imported, SESSION_LIFETIME};
+use super::source::Query;
+use super::spec::Keyring;
+use super::utils::{chromium_time_to_unix, open_immutable, unix_to_chromium_time};
+use crate::{Error, Result};
+
+/// Native reader of a Chromium-family profile.
+///
+/// Opening the database and unwrapping the key are platform specific: DPAPI and AES-GCM
+/// on Windows, a fixed password or the keyring and AES-CBC on Linux, the Keychain on macOS.
+pub struct Chromium {
+    pub name: String,
+    pub(super) profile_path: PathBuf,
+    pub(super) keyring: Option<Keyring>,
+}
+
+impl TryFrom<&str> for Chromium {
+    type Error = Error;
+
+    fn try_from(name: &str) -> Result<Self> {
+        match user_data_dir(name) {
+            Some(user_data) => Ok(Chromium {
+                name: name.to_string(),
+                profile_path: user_data.join("Default/"),
+                keyring: None,
+            }),
+            None => Err(Error::UnsupportedBrowser(name.to_string())),
+        }
+    }
+}
+
+impl Chromium {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            name: browser_of(&path).unwrap_or("Chrome").to_string(),
+            profile_path: path,
+            keyring: None,
+        }
+    }
+    /// Read the key from this keyring instead of guessing it; only used on Linux.
+    pub fn with_keyring(mut self, keyring: Option<Keyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+    pub fn get_site_cookie(&self, host: &str) -> Result<String> {
+        Ok(self.get_site_cookies(host)?.to_string())
+    }
+    pub fn get_site_cookies(&self, host: &str) -> Result<SiteCookie> {
+        self.query_cookies("where host_key = ?1 or host_key = '.' || ?1", [host])
+    }
+    /// Every cookie in the profile.
+    pub fn get_all_cookies(&self) -> Result<SiteCookie> {
+        self.query_cookies("", params![])
+    }
+    fn query_cookies<P: Params>(&self, filter: &str, params: P) -> Result<SiteCookie> {
+        let conn = self.get_cookies_connection()?;
+
+        let keys = self.keys()?;
+        let statement = format!("SELECT host_key, path, name, value, encrypted_value, expires_utc, is_secure, is_httponly, samesite, creation_utc, last_access_utc FROM cookies {filter}");
+
+        let mut stmt = conn.prepare(&statement)?;
+        let rows = stmt.query_map(params, |row: &Row| {
+            Ok(Cookie {
+                host: row.get(0)?,
+                path: row.get(1)?,
+                name: row.get(2)?,
+                value: row.get(3)?,
+                encrypted_value: row.get(4)?,
+                expires: chromium_time_to_unix(row.get(5)?),
+                secure: row.get(6)?,
+                http_only: row.get(7)?,
+                same_site: SameSite::from_db(row.get(8)?),
+                creation: chromium_time_to_unix(row.get(9)?),
+                last_access: chromium_time_to_unix(row.get(10)?),
+            })
+        })?;
+        let mut site_cookie = SiteCookie::new();
+        for cookie in rows {
+            if cookie.is_err() {
+                continue;
+            }
+            let mut cookie = cookie?;
+            if !cookie.value.is_empty() && cookie.encrypted_value.is_empty() {
+                site_cookie.push(cookie);
+                continue;
+            }
+            match decrypt_value(&cookie, &keys) {
+                Ok(value) => {
+                    cookie.value = value;
+                    site_cookie.push(cookie);
+                }
+                // every cookie would fail the same way
+                Err(err @ Error::KeyUnavailable(_)) => return Err(err),
+                Err(err) => site_cookie.push_warning(err.to_string()),
+            }
+        }
+        Ok(site_cookie)
+    }
+
+    /// Encrypt cookies with the key of the profile and write them into its `cookies` table,
+    /// replacing cookies with the same host, name and path.
+    ///
+    /// Values are encrypted like the ones already stored (`v11` on Linux when the keyring
+    /// is used) and prefixed with the SHA-256 of the host from database version 24. The
+    /// columns of older and newer schemas are filled as far as they are known. Refuses to
+    /// write while the browser runs, and backs up the database first. Session cookies are
+    /// given [`SESSION_LIFETIME`], as browsers drop them on restart.
+    pub fn import_cookies(&self, cookies: &[Cookie]) -> Result<Imported> {
+        self.replace_cookies(&[], cookies)
+    }
+
+    /// Like [`Chromium::import_cookies`], first deleting the cookies matching any of the
+    /// queries in the same transaction.
+    pub fn replace_cookies(&self, queries: &[Query], cookies: &[Cookie]) -> Result<Imported> {
+        let profile = ChromiumProfile::from_path(&self.profile_path);
+        if profile.is_locked() {
+            return Err(Error::Locked(format!(
+                "{} is running; close it before importing",
+                self.name
+            )));
+        }
+        #[cfg(target_os = "windows")]
+        if !self.is_v10()? {
+            return Err(Error::KeyUnavailable(
+                "v20 app-bound cookies cannot be written".into(),
+            ));
+        }
+        let path = profile.cookies_path();
+        if !path.exists() {
+            return Err(Error::NotFound(path.display().to_string()));
+        }
+        let mut conn = Connection::open(&path)?;
+        let columns = table_columns(&conn)?;
+        if columns.is_empty() {
+            return Err(Error::Schema(format!(
+                "{} has no cookies table",
+                path.display()
+            )));
+        }
+        let version: i64 = conn
+            .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?
+            .and_then(|version| version.parse().ok())
+            .unwrap_or_default();
+        let like: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT encrypted_value FROM cookies WHERE length(encrypted_value) > 3 ORDER BY creation_utc DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let keys = self.keys()?;
+        let mut imported = Imported {
+            backup: Some(backup_database(&conn, &path)?),
+            ..Default::default()
+        };
+        let now = now();
+        let tx = conn.transaction()?;
+        if !queries.is_empty() {
+            for (rowid, cookie) in matching_rows(&tx, queries)? {
+                tx.execute("DELETE FROM cookies WHERE rowid = ?1", [rowid])?;
+                imported.deleted.push(cookie);
+            }
+        }
+        for (index, cookie) in cookies.iter().enumerate() {
+            if let Some(reason) = invalid(cookie) {
+                imported.skipped.push(reason);
+                continue;
+            }
+            let mut plain = vec![];
+            if version >= 24 {
+                plain.extend(Sha256::digest(cookie.host.as_bytes()));
+            }
+            plain.extend(cookie.value.as_bytes());
+            let encrypted = keys.encrypt(&plain, like.as_deref())?;
+            // creation_utc orders cookies of the same name; offsets keep the input order
+            let creation = unix_to_chromium_time(cookie.creation.unwrap_or(now)) + index as i64;
+            let last_access = unix_to_chromium_time(cookie.last_access.unwrap_or(now));
+            let expires = unix_to_chromium_time(cookie.expires.unwrap_or(now + SESSION_LIFETIME));
+            let same_site = match cookie.same_site {
+                SameSite::Unspecified => -1,
+                SameSite::None => 0,
+                SameSite::Lax => 1,
+                SameSite::Strict => 2,
+            };
+            // source_scheme: 1 for http, 2 for https
+            let (scheme, port) = if cookie.secure { (2, 443) } else { (1, 80) };
+            let values: Vec<(&str, rusqlite::types::Value)> = vec![
+                ("creation_utc", creation.into()),
+                ("host_key", cookie.host.clone().into()),
+                ("top_frame_site_key", String::new().into()),
+                ("name", cookie.name.clone().into()),
+                ("value", String::new().into()),
+                ("encrypted_value", encrypted.into()),
+                ("path", cookie.path.clone().into()),
+                ("expires_utc", expires.into()),
+                ("is_secure", i64::from(cookie.secure).into()),
+                ("is_httponly", i64::from(cookie.http_only).into()),
+                ("last_access_utc", last_access.into()),
+                ("has_expires", 1.into()),
+                ("is_persistent", 1.into()),
+                // medium
+                ("priority", 1.into()),
+                ("samesite", same_site.into()),
+                // the name before samesite
+                ("firstpartyonly", same_site.max(0).into()),
+                ("source_scheme", scheme.into()),
+                ("source_port", port.into()),
+                ("is_same_party", 0.into()),
+                ("last_update_utc", last_access.into()),
+                // unknown
+                ("source_type", 0.into()),
+                ("has_cross_site_ancestor", 0.into()),
+            ];
+            let (names, values): (Vec<_>, Vec<_>) = values
+                .into_iter()
+                .filter(|(name, _)| columns.iter().any(|column| column == name))
+                .unzip();
+            let deleted = tx.execute(
+                "DELETE FROM cookies WHERE host_key = ?1 AND name = ?2 AND path = ?3",
+                params![cookie.host, cookie.name, cookie.path],
+            )?;
+            let placeholders = vec!["?"; names.len()].join(", ");
+            tx.execute(
+                &format!(
+                    "INSERT INTO cookies ({}) VALUES ({})",
+                    names.join(", "),
+                    placeholders
+                ),
+                rusqlite::params_from_iter(values),
+            )?;
+            if deleted > 0 {
+                imported.updated += 1;
+            } else {
+                imported.inserted += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(imported)
+    }
+
+    /// Delete the cookies matching any of the queries, or only list them with `dry_run`. No key is
+    /// needed, as values are not read.
+    ///
+    /// Refuses to write while the browser runs, and backs up the database before deleting
+    /// anything.
+    pub fn delete_cookies(&self, queries: &[Query], dry_run: bool) -> Result<Deleted> {
+        let profile = ChromiumProfile::from_path(&self.profile_path);
+        if !dry_run && profile.is_locked() {
+            return Err(Error::Locked(format!(
+                "{} is running; close it before deleting cookies",
+                self.name
+            )));
+        }
+        let path = profile.cookies_path();
+        if !path.exists() {
+            return Err(Error::NotFound(path.display().to_string()));
+        }
+        // listing must not touch the live database of a running browser
+        let mut conn = if dry_run {
+            open_immutable(&path)?
+        } else {
+            Connection::open(&path)?
+        };
+        let rows = matching_rows(&conn, queries)?;
+        let mut deleted = Deleted::default();
+        if !dry_run && !rows.is_empty() {
+            deleted.backup = Some(backup_database(&conn, &path)?);
+            let tx = conn.transaction()?;
+            for (rowid, _) in &rows {
+                tx.execute("DELETE FROM cookies WHERE rowid = ?1", [rowid])?;
+            }
+            tx.commit()?;
+        }
+        deleted.cookies = rows.into_iter().map(|(_, cookie)| cookie).collect();
+        Ok(deleted)
+    }
+}
+
+/// The `rowid`, host, path and name of the rows matching any of the queries.
+fn matching_rows(conn: &Connection, queries: &[Query]) -> Result<Vec<(i64, Cookie)>> {
+    let mut stmt = conn.prepare("SELECT rowid, host_key, path, name FROM cookies")?;
+    let found = stmt.query_map([], |row| {
+        let cookie = Cookie {
+            host: row.get(1)?,
+            path: row.get(2)?,
+            name: row.get(3)?,
+            ..Default::default()
+        };
+        Ok((row.get::<_, i64>(0)?, cookie))
+    })?;
+    Ok(found
+        .flatten()
+        .filter(|(_, cookie)| queries.iter().any(|query| query.matches(cookie)))
+        .collect())
+}
+
+fn table_columns(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('cookies')")?;
+    let columns = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+fn decrypt_value(cookie: &Cookie, keys: &Keys) -> Result<String> {
+    let value = match keys.decrypt(&cookie.encrypted_value) {
+        Ok(value) => value,
+        Err(Error::DecryptFailed(_)) => {
+            return Err(Error::DecryptFailed(format!(
+                "cookie {} of {}",
+                cookie.name, cookie.host
+            )))
+        }
+        Err(err) => return Err(err),
+    };
+    // since database version 24 the value is prefixed with the SHA-256 of the host
+    let hash = Sha256::digest(cookie.host.as_bytes());
+    let value = match value.strip_prefix(hash.as_slice()) {
+        Some(value) => value.to_vec(),
+        None => value,
+    };
+    String::from_utf8(value)
+        .map_err(|_| Error::DecryptFailed(format!("cookie {} of {}", cookie.name, cookie.host)))
+}
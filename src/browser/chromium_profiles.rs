@@ -0,0 +1,315 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::profiles::Encryption;
+use super::utils::{is_file_held, is_lock_link_held, open_immutable};
+use crate::{Error, Result};
+
+/// Name and user data directory of a Chromium-family browser on Windows, Linux and macOS.
+///
+/// Windows paths are relative to `AppData/Local`, the others to the config directory.
+struct ChromiumBrowser {
+    name: &'static str,
+    windows: &'static str,
+    linux: &'static str,
+    macos: &'static str,
+}
+
+const CHROMIUM_BROWSERS: &[ChromiumBrowser] = &[
+    ChromiumBrowser {
+        name: "Chrome",
+        windows: "Google/Chrome/User Data",
+        linux: "google-chrome",
+        macos: "Google/Chrome",
+    },
+    ChromiumBrowser {
+        name: "Chrome Beta",
+        windows: "Google/Chrome Beta/User Data",
+        linux: "google-chrome-beta",
+        macos: "Google/Chrome Beta",
+    },
+    ChromiumBrowser {
+        name: "Chromium",
+        windows: "Chromium/User Data",
+        linux: "chromium",
+        macos: "Chromium",
+    },
+    ChromiumBrowser {
+        name: "Edge",
+        windows: "Microsoft/Edge/User Data",
+        linux: "microsoft-edge",
+        macos: "Microsoft Edge",
+    },
+    ChromiumBrowser {
+        name: "Brave",
+        windows: "BraveSoftware/Brave-Browser/User Data",
+        linux: "BraveSoftware/Brave-Browser",
+        macos: "BraveSoftware/Brave-Browser",
+    },
+    ChromiumBrowser {
+        name: "Vivaldi",
+        windows: "Vivaldi/User Data",
+        linux: "vivaldi",
+        macos: "Vivaldi",
+    },
+];
+
+fn find_browser(name: &str) -> Option<&'static ChromiumBrowser> {
+    CHROMIUM_BROWSERS
+        .iter()
+        .find(|b| b.name.eq_ignore_ascii_case(name))
+}
+
+/// Names of the supported Chromium-family browsers.
+pub fn chromium_browsers() -> Vec<&'static str> {
+    CHROMIUM_BROWSERS.iter().map(|b| b.name).collect()
+}
+
+/// The browser whose `User Data` directory holds the profile at `path`.
+pub fn browser_of(path: &Path) -> Option<&'static str> {
+    let user_data = path.parent()?;
+    CHROMIUM_BROWSERS
+        .iter()
+        .find(|b| user_data_dir(b.name).is_some_and(|dir| dir == user_data))
+        .map(|b| b.name)
+}
+
+/// The `User Data` directory of a Chromium-family browser, or `None` if the name is unknown.
+pub fn user_data_dir(browser: &str) -> Option<PathBuf> {
+    let browser = find_browser(browser)?;
+    if cfg!(target_os = "windows") {
+        Some(dirs::data_local_dir()?.join(browser.windows))
+    } else if cfg!(target_os = "macos") {
+        Some(dirs::config_dir()?.join(browser.macos))
+    } else {
+        Some(dirs::config_dir()?.join(browser.linux))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChromiumProfile {
+    /// Browser name, as in [`chromium_browsers`].
+    pub browser: String,
+    /// Directory name inside `User Data`, like `Default` or `Profile 1`.
+    pub dir: String,
+    /// Display name shown in the profile menu.
+    pub name: String,
+    /// Email of the signed-in account.
+    pub email: Option<String>,
+    pub path: PathBuf,
+}
+
+impl ChromiumProfile {
+    /// A profile known only by its directory, like one passed with `-p`.
+    pub fn from_path(path: &Path) -> Self {
+        let dir = path
+            .file_name()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self {
+            browser: String::new(),
+            name: dir.clone(),
+            dir,
+            email: None,
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn user_data_dir(&self) -> &Path {
+        self.path.parent().unwrap_or(&self.path)
+    }
+
+    /// `Network/Cookies`, or `Cookies` for profiles created before Chrome 96.
+    pub fn cookies_path(&self) -> PathBuf {
+        let path = self.path.join("Network/Cookies");
+        if path.exists() || !self.path.join("Cookies").exists() {
+            path
+        } else {
+            self.path.join("Cookies")
+        }
+    }
+
+    /// Whether a running browser holds the `User Data` directory.
+    pub fn is_locked(&self) -> bool {
+        if cfg!(target_os = "windows") {
+            is_file_held(&self.user_data_dir().join("lockfile"))
+        } else {
+            is_lock_link_held(&self.user_data_dir().join("SingletonLock"))
+        }
+    }
+
+    /// Encryption schemes of the stored cookie values.
+    ///
+    /// The version prefixes of `encrypted_value` are read from the database. When it cannot
+    /// be read or holds no cookie, the scheme is inferred from the keys in `Local State`.
+    pub fn encryption(&self) -> Vec<Encryption> {
+        let mut schemes = vec![];
+        if let Ok(conn) = open_immutable(&self.cookies_path()) {
+            let prefixes = conn
+                .prepare("SELECT DISTINCT substr(encrypted_value, 1, 3) FROM cookies")
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| row.get::<_, Vec<u8>>(0))?
+                        .collect::<rusqlite::Result<Vec<_>>>()
+                });
+            for prefix in prefixes.unwrap_or_default() {
+                let scheme = match prefix.as_slice() {
+                    b"" => Encryption::Plain,
+                    b"v10" => Encryption::V10,
+                    b"v11" => Encryption::V11,
+                    b"v20" => Encryption::V20,
+                    _ => continue,
+                };
+                if !schemes.contains(&scheme) {
+                    schemes.push(scheme);
+                }
+            }
+        }
+        if schemes.is_empty() {
+            let local_state = fs::File::open(self.user_data_dir().join("Local State"))
+                .ok()
+                .and_then(|file| serde_json::from_reader::<_, serde_json::Value>(file).ok());
+            if let Some(json) = local_state {
+                if !json["os_crypt"]["app_bound_encrypted_key"].is_null() {
+                    schemes.push(Encryption::V20);
+                } else if !json["os_crypt"]["encrypted_key"].is_null() {
+                    schemes.push(Encryption::V10);
+                }
+            }
+        }
+        schemes.sort();
+        schemes
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        self.dir.eq_ignore_ascii_case(query)
+            || self.name.eq_ignore_ascii_case(query)
+            || self
+                .email
+                .as_ref()
+                .is_some_and(|email| email.eq_ignore_ascii_case(query))
+    }
+}
+
+/// Read the profiles of a `User Data` directory from `profile.info_cache` in `Local State`.
+///
+/// Profile directories are listed in their on-disk order (`Default`, `Profile 1`, ...).
+pub fn read_profiles(browser: &str, user_data: &Path) -> Result<Vec<ChromiumProfile>> {
+    let local_state = user_data.join("Local State");
+    let file = fs::File::open(&local_state)
+        .map_err(|_| Error::NotFound(local_state.display().to_string()))?;
+    let json: serde_json::Value = serde_json::from_reader(file)?;
+    let mut profiles: Vec<ChromiumProfile> = match json["profile"]["info_cache"].as_object() {
+        Some(cache) => cache
+            .iter()
+            .map(|(dir, info)| ChromiumProfile {
+                browser: browser.to_string(),
+                dir: dir.clone(),
+                name: info["name"].as_str().unwrap_or(dir).to_string(),
+                email: info["user_name"]
+                    .as_str()
+                    .filter(|email| !email.is_empty())
+                    .map(String::from),
+                path: user_data.join(dir),
+            })
+            .collect(),
+        None => vec![],
+    };
+    // an unsigned, never renamed Default profile may be missing from info_cache
+    if !profiles.iter().any(|p| p.dir == "Default") && user_data.join("Default").is_dir() {
+        profiles.push(ChromiumProfile {
+            browser: browser.to_string(),
+            dir: "Default".to_string(),
+            name: "Default".to_string(),
+            email: None,
+            path: user_data.join("Default"),
+        });
+    }
+    profiles.sort_by_key(|p| (p.dir != "Default", profile_number(&p.dir), p.dir.clone()));
+    Ok(profiles)
+}
+
+fn profile_number(dir: &str) -> u32 {
+    dir.strip_prefix("Profile ")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u32::MAX)
+}
+
+/// Every profile of every installed Chromium-family browser.
+pub fn find_profiles() -> Vec<ChromiumProfile> {
+    CHROMIUM_BROWSERS
+        .iter()
+        .filter_map(|b| read_profiles(b.name, &user_data_dir(b.name)?).ok())
+        .flatten()
+        .collect()
+}
+
+/// Find a profile by its directory name, display name or signed-in account email.
+pub fn find_profile_in<'a>(
+    profiles: &'a [ChromiumProfile],
+    query: &str,
+) -> Option<&'a ChromiumProfile> {
+    profiles.iter().find(|p| p.matches(query))
+}
+
+/// Resolve a profile of a Chromium-family browser, defaulting to `Default`.
+pub fn resolve_profile(browser: &str, profile: Option<&str>) -> Result<ChromiumProfile> {
+    let user_data = user_data_dir(browser).ok_or_else(|| {
+        Error::UnsupportedBrowser(format!(
+            "{}; please use {}",
+            browser,
+            chromium_browsers().join(", ")
+        ))
+    })?;
+    let browser = find_browser(browser).map_or(browser, |b| b.name);
+    let profiles = read_profiles(browser, &user_data)?;
+    let query = profile.unwrap_or("Default");
+    find_profile_in(&profiles, query)
+        .cloned()
+        .ok_or_else(|| Error::NotFound(format!("{} profile {}", browser, query)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_profiles_ok() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("Default")).unwrap();
+        fs::write(
+            root.join("Local State"),
+            r#"{"profile": {"info_cache": {
+                "Profile 10": {"name": "Test", "user_name": ""},
+                "Profile 3": {"name": "Work", "user_name": "alice@corp.com"},
+                "Profile 1": {"name": "Home", "user_name": "alice@gmail.com"}
+            }}}"#,
+        )
+        .unwrap();
+        let profiles = read_profiles("Chrome", root).unwrap();
+        let dirs: Vec<&str> = profiles.iter().map(|p| p.dir.as_str()).collect();
+        assert_eq!(dirs, ["Default", "Profile 1", "Profile 3", "Profile 10"]);
+        assert_eq!(profiles[3].email, None);
+
+        let work = find_profile_in(&profiles, "work").unwrap();
+        assert_eq!(work.path, root.join("Profile 3"));
+        assert_eq!(
+            find_profile_in(&profiles, "ALICE@corp.com").unwrap().dir,
+            "Profile 3"
+        );
+        assert_eq!(
+            find_profile_in(&profiles, "profile 1").unwrap().name,
+            "Home"
+        );
+        assert!(find_profile_in(&profiles, "nobody").is_none());
+    }
+
+    #[test]
+    fn user_data_dir_ok() {
+        assert!(user_data_dir("edge").is_some());
+        assert!(user_data_dir("chrome beta").is_some());
+        assert!(user_data_dir("netscape").is_none());
+    }
+}
@@ -0,0 +1,283 @@
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use rusqlite::Connection;
+use sha1::Sha1;
+use std::{cell::OnceCell, process::Command};
+
+use super::chromium::Chromium;
+use super::chromium_profiles::ChromiumProfile;
+use super::spec::Keyring;
+use super::utils::open_immutable;
+use crate::{Error, Result};
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+
+#[cfg(target_os = "macos")]
+const ITERATIONS: u32 = 1003;
+#[cfg(not(target_os = "macos"))]
+const ITERATIONS: u32 = 1;
+
+/// Derive the AES-128 key from the password stored by the browser.
+fn derive_key(password: &[u8]) -> Vec<u8> {
+    let mut key = vec![0; 16];
+    pbkdf2::pbkdf2_hmac::<Sha1>(password, b"saltysalt", ITERATIONS, &mut key);
+    key
+}
+
+/// Keys of the `v10` and `v11` cookies.
+///
+/// On Linux `v10` uses the fixed password `peanuts` and `v11` the password in the keyring,
+/// which is only needed, and only looked up, when such cookies exist. On macOS `v10` uses
+/// the password in the Keychain.
+pub struct Keys {
+    v10: Vec<u8>,
+    /// Filled from the keyring on the first `v11` value.
+    v11: OnceCell<std::result::Result<Vec<u8>, String>>,
+    browser: String,
+    keyring: Option<Keyring>,
+}
+
+impl Keys {
+    /// The key derived from the password in the Keychain or keyring, looked up now unless
+    /// a `v11` value already needed it.
+    pub fn stored(&self) -> Result<&[u8]> {
+        if cfg!(target_os = "macos") {
+            return Ok(&self.v10);
+        }
+        self.keyring_key()
+            .map_err(|err| Error::KeyUnavailable(err.clone()))
+    }
+
+    fn keyring_key(&self) -> std::result::Result<&[u8], &String> {
+        self.v11
+            .get_or_init(|| password(&self.browser, self.keyring).map(|p| derive_key(&p)))
+            .as_deref()
+    }
+
+    fn v11(&self) -> Result<&[u8]> {
+        self.keyring_key()
+            .map_err(|err| Error::KeyUnavailable(format!("v11 cookies need the keyring: {}", err)))
+    }
+
+    /// Decrypt an `encrypted_value`: a version prefix, then AES-128-CBC.
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
+        let key = match encrypted.get(..3) {
+            Some(b"v10") => self.v10.as_slice(),
+            Some(b"v11") => self.v11()?,
+            Some(b"v20") => {
+                return Err(Error::DecryptFailed(
+                    "v20 cookies are only used on Windows".into(),
+                ))
+            }
+            _ => return Err(Error::DecryptFailed("unknown encryption".into())),
+        };
+        Aes128CbcDec::new(key.into(), &[b' '; 16].into())
+            .decrypt_padded_vec_mut::<Pkcs7>(&encrypted[3..])
+            .map_err(|_| Error::DecryptFailed("wrong key or corrupt value".into()))
+    }
+
+    /// Encrypt a value the way [`Keys::decrypt`] reads it, as `v11` when `like`, a value
+    /// already in the database, is `v11`, and as `v10` otherwise.
+    pub fn encrypt(&self, plain: &[u8], like: Option<&[u8]>) -> Result<Vec<u8>> {
+        let (version, key) = match like.and_then(|like| like.get(..3)) {
+            Some(b"v11") => (b"v11", self.v11()?),
+            _ => (b"v10", self.v10.as_slice()),
+        };
+        let mut value = version.to_vec();
+        value.extend(
+            Aes128CbcEnc::new(key.into(), &[b' '; 16].into())
+                .encrypt_padded_vec_mut::<Pkcs7>(plain),
+        );
+        Ok(value)
+    }
+}
+
+/// Browser name used for the keyring entry, `<name> Safe Storage`.
+fn keyring_name(browser: &str) -> &'static str {
+    match browser.to_lowercase().as_str() {
+        "chromium" => "Chromium",
+        "brave" => "Brave",
+        "edge" if cfg!(target_os = "macos") => "Microsoft Edge",
+        "edge" => "Chromium",
+        "vivaldi" if cfg!(target_os = "macos") => "Vivaldi",
+        _ => "Chrome",
+    }
+}
+
+fn run(command: &mut Command) -> std::result::Result<Vec<u8>, String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .map_err(|err| format!("cannot run {}: {}", program, err))?;
+    let mut password = output.stdout;
+    while password.last().is_some_and(u8::is_ascii_whitespace) {
+        password.pop();
+    }
+    if !output.status.success() || password.is_empty() {
+        return Err(format!("{} found no password", program));
+    }
+    Ok(password)
+}
+
+#[cfg(target_os = "macos")]
+fn password(browser: &str, _keyring: Option<Keyring>) -> std::result::Result<Vec<u8>, String> {
+    let service = format!("{} Safe Storage", keyring_name(browser));
+    run(Command::new("security").args(["find-generic-password", "-w", "-s", &service]))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn password(browser: &str, keyring: Option<Keyring>) -> std::result::Result<Vec<u8>, String> {
+    let name = keyring_name(browser);
+    let gnome =
+        || run(Command::new("secret-tool").args(["lookup", "application", &name.to_lowercase()]));
+    let kwallet = || {
+        run(Command::new("kwallet-query").args([
+            "--read-password",
+            &format!("{} Safe Storage", name),
+            "--folder",
+            &format!("{} Keys", name),
+            "kdewallet",
+        ]))
+    };
+    match keyring {
+        Some(Keyring::BasicText) => Err("the basictext store has no v11 password".into()),
+        Some(Keyring::GnomeKeyring) => gnome(),
+        Some(Keyring::KWallet | Keyring::KWallet5 | Keyring::KWallet6) => kwallet(),
+        None => {
+            gnome().or_else(|gnome| kwallet().map_err(|kwallet| format!("{}; {}", gnome, kwallet)))
+        }
+    }
+}
+
+impl Chromium {
+    /// The key stored in the Keychain on macOS, or in the keyring on Linux.
+    pub fn get_key(&self) -> Result<Vec<u8>> {
+        password(&self.name, self.keyring)
+            .map(|password| derive_key(&password))
+            .map_err(Error::KeyUnavailable)
+    }
+    pub fn keys(&self) -> Result<Keys> {
+        let (v10, v11) = if cfg!(target_os = "macos") {
+            (
+                self.get_key()?,
+                OnceCell::from(Err("v11 is not used on macOS".into())),
+            )
+        } else {
+            (derive_key(b"peanuts"), OnceCell::new())
+        };
+        Ok(Keys {
+            v10,
+            v11,
+            browser: self.name.clone(),
+            keyring: self.keyring,
+        })
+    }
+    pub fn get_cookies_connection(&self) -> Result<Connection> {
+        let path = ChromiumProfile::from_path(&self.profile_path).cookies_path();
+        if !path.exists() {
+            return Err(Error::NotFound(path.display().to_string()));
+        }
+        Ok(open_immutable(&path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_v10_ok() {
+        let key = derive_key(b"peanuts");
+        let mut blob = b"v10".to_vec();
+        blob.extend(
+            Aes128CbcEnc::new(key.as_slice().into(), &[b' '; 16].into())
+                .encrypt_padded_vec_mut::<Pkcs7>(b"hello"),
+        );
+        let keys = Keys {
+            v10: key,
+            v11: OnceCell::from(Err("none".into())),
+            browser: "chrome".into(),
+            keyring: None,
+        };
+        assert_eq!(keys.decrypt(&blob).unwrap(), b"hello");
+        blob[0..3].copy_from_slice(b"v11");
+        assert!(matches!(keys.decrypt(&blob), Err(Error::KeyUnavailable(_))));
+        assert!(matches!(keys.decrypt(b"v10"), Err(Error::DecryptFailed(_))));
+    }
+
+    #[test]
+    fn encrypt_ok() {
+        let keys = Keys {
+            v10: derive_key(b"peanuts"),
+            v11: OnceCell::from(Ok(derive_key(b"secret"))),
+            browser: "chrome".into(),
+            keyring: None,
+        };
+        let v10 = keys.encrypt(b"hello", None).unwrap();
+        assert!(v10.starts_with(b"v10"));
+        assert_eq!(keys.decrypt(&v10).unwrap(), b"hello");
+        let v11 = keys.encrypt(b"hello", Some(b"v11...")).unwrap();
+        assert!(v11.starts_with(b"v11"));
+        assert_eq!(keys.decrypt(&v11).unwrap(), b"hello");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn import_cookies_ok() {
+        use super::super::cookie::Cookie;
+        use super::super::source::Query;
+
+        let dir = tempfile::tempdir().unwrap();
+        let profile = dir.path().join("Default");
+        std::fs::create_dir_all(profile.join("Network")).unwrap();
+        let conn = Connection::open(profile.join("Network/Cookies")).unwrap();
+        conn.execute_batch("CREATE TABLE meta(key LONGVARCHAR NOT NULL UNIQUE PRIMARY KEY, value LONGVARCHAR);
+            INSERT INTO meta VALUES ('version', '24');
+            CREATE TABLE cookies(creation_utc INTEGER NOT NULL,host_key TEXT NOT NULL,top_frame_site_key TEXT NOT NULL,name TEXT NOT NULL,value TEXT NOT NULL,encrypted_value BLOB NOT NULL,path TEXT NOT NULL,expires_utc INTEGER NOT NULL,is_secure INTEGER NOT NULL,is_httponly INTEGER NOT NULL,last_access_utc INTEGER NOT NULL,has_expires INTEGER NOT NULL,is_persistent INTEGER NOT NULL,priority INTEGER NOT NULL,samesite INTEGER NOT NULL,source_scheme INTEGER NOT NULL,source_port INTEGER NOT NULL,last_update_utc INTEGER NOT NULL,source_type INTEGER NOT NULL,has_cross_site_ancestor INTEGER NOT NULL);
+            CREATE UNIQUE INDEX cookies_unique_index ON cookies(host_key, top_frame_site_key, has_cross_site_ancestor, name, path, source_scheme, source_port);
+            INSERT INTO cookies VALUES (0, '.bing.com', '', 'sid', 'old', x'', '/', 0, 1, 1, 0, 1, 1, 1, -1, 2, 443, 0, 0, 0);").unwrap();
+        drop(conn);
+        let cookie = |name: &str, value: &str| Cookie {
+            host: ".bing.com".into(),
+            path: "/".into(),
+            name: name.into(),
+            value: value.into(),
+            expires: Some(1_800_000_000),
+            secure: true,
+            ..Default::default()
+        };
+        let chromium = Chromium::new(profile).with_keyring(Some(Keyring::BasicText));
+        let imported = chromium
+            .import_cookies(&[cookie("sid", "new"), cookie("lang", "en")])
+            .unwrap();
+        assert_eq!((imported.inserted, imported.updated), (1, 1));
+        assert!(imported.backup.unwrap().exists());
+        let read = chromium.get_site_cookies("bing.com").unwrap();
+        let sid = read.iter().find(|cookie| cookie.name == "sid").unwrap();
+        assert_eq!(
+            (sid.value.as_str(), sid.expires),
+            ("new", Some(1_800_000_000))
+        );
+        assert_eq!(read.len(), 2);
+
+        let query = Query {
+            names: vec!["lang".into()],
+            ..Query::site("bing.com").unwrap()
+        };
+        let listed = chromium
+            .delete_cookies(std::slice::from_ref(&query), true)
+            .unwrap();
+        assert_eq!((listed.cookies.len(), listed.backup), (1, None));
+        assert_eq!(chromium.get_site_cookies("bing.com").unwrap().len(), 2);
+        let deleted = chromium.delete_cookies(&[query], false).unwrap();
+        assert_eq!(deleted.cookies[0].name, "lang");
+        assert!(deleted.backup.unwrap().exists());
+        assert_eq!(chromium.get_site_cookie("bing.com").unwrap(), "sid=new");
+
+        let replaced = chromium
+            .replace_cookies(&[Query::site("bing.com").unwrap()], &[cookie("lang", "de")])
+            .unwrap();
+        assert_eq!((replaced.deleted.len(), replaced.inserted), (1, 1));
+        assert_eq!(chromium.get_site_cookie("bing.com").unwrap(), "lang=de");
+    }
+}
@@ -1,160 +1,127 @@
-use rusqlite::{Connection, Result as SqlResult, Row};
-use std::fs::remove_file;
-use std::path::PathBuf;
 use base64::{engine::general_purpose, Engine as _};
-use sha2::{Sha256, Digest};
-
+use rusqlite::Connection;
+use std::fs::remove_file;
+use std::path::{Path, PathBuf};
 
-use super::cookie::{Cookie, SiteCookie};
+use super::chromium::Chromium;
+use super::chromium_profiles::ChromiumProfile;
 use crate::windows::{
-    aes_gcm_decrypt, crypt_unprotect_data, is_elevated, rawcopy, release_file_lock,
+    aes_gcm_decrypt, aes_gcm_encrypt, crypt_unprotect_data, is_elevated, rawcopy, release_file_lock,
 };
+use crate::{Error, Result};
 
-pub struct Chromium {
-    pub name: String,
-    profile_path: PathBuf,
+/// Key of the `v10` cookies, unprotected with DPAPI.
+pub struct Keys {
+    key: Vec<u8>,
 }
 
-impl From<&str> for Chromium {
-    fn from(name: &str) -> Self {
-        let home_dir = dirs::home_dir().unwrap();
-        match name.to_lowercase().as_ref() {
-            "chrome" => Chromium {
-                name: name.to_string(),
-                profile_path: home_dir.join("AppData/Local/Google/Chrome/User Data/Default/"),
-            },
-            "chrome beta" => Chromium {
-                name: name.to_string(),
-                profile_path: home_dir.join("AppData/Local/Google/Chrome Beta/User Data/Default/"),
-            },
-            "chromium" => Chromium {
-                name: name.to_string(),
-                profile_path: home_dir.join("AppData/Local/Chromium/User Data/Default/"),
-            },
-            "edge" => Chromium {
-                name: name.to_string(),
-                profile_path: home_dir.join("AppData/Local/Microsoft/Edge/User Data/Default/"),
-            },
-            _ => panic!("invalid browser"),
+impl Keys {
+    /// Decrypt an `encrypted_value`: `v10`, a 12 bytes nonce, then AES-256-GCM.
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
+        if encrypted.len() < 15 {
+            return Err(Error::DecryptFailed("value is truncated".into()));
         }
+        aes_gcm_decrypt(&encrypted[15..], &self.key, &encrypted[3..15])
+            .map_err(|err| Error::DecryptFailed(err.to_string()))
+    }
+
+    /// Encrypt a value the way [`Keys::decrypt`] reads it; `v10` is the only version.
+    pub fn encrypt(&self, plain: &[u8], _like: Option<&[u8]>) -> Result<Vec<u8>> {
+        let (nonce, encrypted) = aes_gcm_encrypt(plain, &self.key)
+            .map_err(|err| Error::KeyUnavailable(format!("cannot encrypt: {}", err)))?;
+        let mut value = b"v10".to_vec();
+        value.extend(nonce);
+        value.extend(encrypted);
+        Ok(value)
     }
 }
 
 impl Chromium {
-    pub fn new(path: PathBuf) -> Self {
-        Self {
-            name: "Chrome".to_string(),
-            profile_path: path,
-        }
+    fn local_state(&self) -> Result<serde_json::Value> {
+        let path = self.profile_path.join("../").join("Local State");
+        let file =
+            std::fs::File::open(&path).map_err(|_| Error::NotFound(path.display().to_string()))?;
+        Ok(serde_json::from_reader(file)?)
     }
-    pub fn is_v10(&self) -> bool {
-        let file = std::fs::File::open(self.profile_path.join("../").join("Local State")).expect("cannot open Local State");
-        let json: serde_json::Value =
-            serde_json::from_reader(file).expect("Local State should be JSON");
+    pub fn is_v10(&self) -> Result<bool> {
+        let json = self.local_state()?;
         let v = &json["os_crypt"]["encrypted_key"];
         let app_bound_encrypted_key = &json["os_crypt"]["app_bound_encrypted_key"];
-        return !v.is_null() && app_bound_encrypted_key.is_null();
+        Ok(!v.is_null() && app_bound_encrypted_key.is_null())
     }
-    pub fn get_key(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let file = std::fs::File::open(self.profile_path.join("../").join("Local State"))?;
-        let json: serde_json::Value =
-            serde_json::from_reader(file).expect("Local State should be JSON");
-        let v = &json["os_crypt"]["encrypted_key"];
-        let v = general_purpose::STANDARD.decode(v.as_str().unwrap())?;
-        Ok(crypt_unprotect_data(&v[5..])?)
+    pub fn get_key(&self) -> Result<Vec<u8>> {
+        let json = self.local_state()?;
+        let v = json["os_crypt"]["encrypted_key"]
+            .as_str()
+            .ok_or_else(|| Error::KeyUnavailable("no encrypted_key in Local State".into()))?;
+        let v = general_purpose::STANDARD
+            .decode(v)
+            .map_err(|err| Error::KeyUnavailable(err.to_string()))?;
+        if v.len() < 5 {
+            return Err(Error::KeyUnavailable("encrypted_key is truncated".into()));
+        }
+        crypt_unprotect_data(&v[5..]).map_err(|err| Error::KeyUnavailable(err.to_string()))
     }
-    pub fn get_app_bound_encrypted_key(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let file = std::fs::File::open(self.profile_path.join("../").join("Local State"))?;
-        let json: serde_json::Value = serde_json::from_reader(file)?;
-        let app_bound_encrypted_key = json["os_crypt"]["app_bound_encrypted_key"].as_str().unwrap();
+    pub fn get_app_bound_encrypted_key(&self) -> Result<Vec<u8>> {
+        let json = self.local_state()?;
+        let app_bound_encrypted_key = json["os_crypt"]["app_bound_encrypted_key"]
+            .as_str()
+            .ok_or_else(|| {
+                Error::KeyUnavailable("no app_bound_encrypted_key in Local State".into())
+            })?;
         if !app_bound_encrypted_key.starts_with("APPB") {
-            return Err("invalid app_bound_encrypted_key".into());
+            return Err(Error::KeyUnavailable(
+                "invalid app_bound_encrypted_key".into(),
+            ));
         }
-        let v = general_purpose::STANDARD.decode(&app_bound_encrypted_key[..])?;
-        Ok(crypt_unprotect_data(&v)?)
+        let v = general_purpose::STANDARD
+            .decode(app_bound_encrypted_key)
+            .map_err(|err| Error::KeyUnavailable(err.to_string()))?;
+        crypt_unprotect_data(&v).map_err(|err| Error::KeyUnavailable(err.to_string()))
     }
-    pub fn get_cookies_connection(&self) -> SqlResult<Connection> {
-        let path = self.profile_path.join("Network/Cookies");
+    pub fn get_cookies_connection(&self) -> Result<Connection> {
+        let path = ChromiumProfile::from_path(&self.profile_path).cookies_path();
         if !path.exists() {
-            return Err(rusqlite::Error::InvalidPath(path));
+            return Err(Error::NotFound(path.display().to_string()));
         }
         if is_elevated() {
-            let tmp_cookie_path = self.get_temp_cookies_path(&path);
-            return Connection::open(&tmp_cookie_path);
+            let tmp_cookie_path = self.get_temp_cookies_path(&path)?;
+            return Ok(Connection::open(&tmp_cookie_path)?);
         }
-        unsafe {
-            release_file_lock(path.as_os_str().to_str().unwrap());
-        }
-        let conn_result = Connection::open(&path);
-        if conn_result.is_ok() {
-            return conn_result;
+        if let Some(p) = path.to_str() {
+            unsafe {
+                release_file_lock(p);
+            }
         }
-        let err = conn_result.unwrap_err();
-        if let rusqlite::Error::SqliteFailure(e, _) = err {
-            if rusqlite::ErrorCode::CannotOpen == e.code {
-                if !is_elevated() {
-                    return Err(rusqlite::Error::SqliteFailure(
-                        e,
-                        Some("Browser has locked cookie, please run as administrator".to_string()),
-                    ));
-                }
-                let tmp_cookie_path = self.get_temp_cookies_path(&path);
-                return Connection::open(&tmp_cookie_path);
+        match Connection::open(&path) {
+            Ok(conn) => Ok(conn),
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if rusqlite::ErrorCode::CannotOpen == e.code =>
+            {
+                Err(Error::Locked(
+                    "Browser has locked cookie, please run as administrator".to_string(),
+                ))
             }
+            Err(err) => Err(err.into()),
         }
-        Err(err)
     }
-    fn get_temp_cookies_path(&self, path: &PathBuf) -> PathBuf {
+    fn get_temp_cookies_path(&self, path: &Path) -> Result<PathBuf> {
         let tmp_dir = std::env::temp_dir();
-        let p = path.as_os_str().to_str().unwrap();
+        let invalid = || Error::NotFound(path.display().to_string());
+        let p = path.to_str().ok_or_else(invalid)?;
         let tmp_cookie_path = tmp_dir.join("Cookies");
         if tmp_cookie_path.exists() {
-            remove_file(&tmp_cookie_path).unwrap();
+            remove_file(&tmp_cookie_path)?;
         }
         // need administrator permission
-        rawcopy(p, tmp_dir.as_os_str().to_str().unwrap()).unwrap();
-        tmp_cookie_path
+        rawcopy(p, tmp_dir.to_str().ok_or_else(invalid)?)
+            .map_err(|err| Error::Locked(format!("cannot copy locked cookie: {}", err)))?;
+        Ok(tmp_cookie_path)
     }
-    pub fn get_site_cookie(&self, host: &str) -> SqlResult<String> {
-        let conn = self.get_cookies_connection()?;
-
-        let key = self.get_key().expect("cannot get key");
-        let statement = format!("SELECT host_key, path, name, value, encrypted_value FROM cookies where host_key = '{host}' or host_key = '.{host}'");
-
-        let mut stmt = conn.prepare(&statement)?;
-        let rows = stmt.query_map([], |row: &Row| {
-            Ok(Cookie {
-                host: row.get(0)?,
-                path: row.get(1)?,
-                name: row.get(2)?,
-                value: row.get(3)?,
-                encrypted_value: row.get(4)?,
-            })
-        })?;
-        let mut site_cookie = SiteCookie::new();
-        for cookie in rows {
-            if cookie.is_err() {
-                continue;
-            }
-            let mut cookie = cookie?;
-            if !cookie.value.is_empty() && cookie.encrypted_value.is_empty() {
-                site_cookie.push(cookie);
-                continue;
-            }
-            let hash = Sha256::digest(cookie.host.as_bytes());
-            let value = &cookie.encrypted_value[15..];
-            let nonce = &cookie.encrypted_value[3..15];
-            let value = aes_gcm_decrypt(value, &key, nonce);
-            if value.starts_with(hash.as_ref()) {
-                cookie.value = String::from_utf8(value[hash.len()..].to_vec())
-                    .expect(&format!("parse hash cookie: {} err", cookie.name));
-            } else {
-                cookie.value = String::from_utf8(value)
-                    .expect(&format!("parse cookie: {} err", cookie.name));
-            }
-            site_cookie.push(cookie);
-        }
-        Ok(site_cookie.to_string())
+    pub fn keys(&self) -> Result<Keys> {
+        Ok(Keys {
+            key: self.get_key()?,
+        })
     }
 }
 
@@ -164,9 +131,9 @@ mod tests {
 
     #[test]
     fn get_key_ok() {
-        let edge: Chromium = "Edge".into();
+        let edge = Chromium::try_from("Edge").unwrap();
         assert!(edge.get_key().is_ok());
-        let chrome: Chromium = "Chrome".into();
+        let chrome = Chromium::try_from("Chrome").unwrap();
         assert!(chrome.get_key().is_ok());
     }
     #[test]
@@ -177,20 +144,20 @@ mod tests {
     }
     #[test]
     fn chrome_connect_sql_ok() {
-        let chrome: Chromium = "Chrome".into();
+        let chrome = Chromium::try_from("Chrome").unwrap();
         let res = chrome.get_site_cookie("example.com");
         assert!(res.is_ok());
         // println!("{}", res.unwrap());
     }
     #[test]
     fn edge_get_app_bound_encrypted_key_ok() {
-        let chrome: Chromium = "edge".into();
+        let chrome = Chromium::try_from("edge").unwrap();
         let key = chrome.get_app_bound_encrypted_key().unwrap();
         println!("{:?}", key);
     }
     #[test]
     fn edge_connect_sql_ok() {
-        let chrome: Chromium = "edge".into();
+        let chrome = Chromium::try_from("edge").unwrap();
         let res = chrome.get_site_cookie("bing.com");
         assert!(res.is_ok());
         // println!("{}", res.unwrap());
@@ -1,18 +1,90 @@
 use std::{fmt, ops};
 
+/// The `SameSite` attribute of a cookie.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SameSite {
+    /// No attribute was set; browsers treat this as `Lax`.
+    #[default]
+    Unspecified,
+    None,
+    Lax,
+    Strict,
+}
+
+impl SameSite {
+    /// Map the `samesite` column of Chromium or the `sameSite` column of Firefox.
+    ///
+    /// Both use `0` for None, `1` for Lax and `2` for Strict; Chromium uses `-1` when
+    /// the attribute is missing.
+    pub fn from_db(value: i64) -> Self {
+        match value {
+            0 => SameSite::None,
+            1 => SameSite::Lax,
+            2 => SameSite::Strict,
+            _ => SameSite::Unspecified,
+        }
+    }
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SameSite::Unspecified => write!(f, "Unspecified"),
+            SameSite::None => write!(f, "None"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::Strict => write!(f, "Strict"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Cookie {
     pub host: String,
     pub path: String,
     pub name: String,
     pub value: String,
     pub encrypted_value: Vec<u8>,
+    /// Expiry as Unix seconds, `None` for session cookies.
+    pub expires: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    /// Creation time as Unix seconds, when the store records it.
+    pub creation: Option<i64>,
+    /// Last access time as Unix seconds, when the store records it.
+    pub last_access: Option<i64>,
 }
 
-pub struct SiteCookie(Vec<Cookie>);
+#[derive(Debug, Clone, Default)]
+pub struct SiteCookie {
+    cookies: Vec<Cookie>,
+    warnings: Vec<String>,
+}
 
 impl SiteCookie {
     pub fn new() -> Self {
-        Self(vec![])
+        Self::default()
+    }
+    /// Problems with single cookies, which were skipped instead of failing the whole site.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+    pub fn push_warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+    /// Append the cookies and warnings of `other`.
+    pub fn merge(&mut self, other: SiteCookie) {
+        self.cookies.extend(other.cookies);
+        self.warnings.extend(other.warnings);
+    }
+}
+
+impl From<Vec<Cookie>> for SiteCookie {
+    fn from(cookies: Vec<Cookie>) -> Self {
+        Self {
+            cookies,
+            warnings: vec![],
+        }
     }
 }
 
@@ -20,12 +92,12 @@ impl ops::Deref for SiteCookie {
     type Target = Vec<Cookie>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.cookies
     }
 }
 impl ops::DerefMut for SiteCookie {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.cookies
     }
 }
 
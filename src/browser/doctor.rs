@@ -0,0 +1,437 @@
+use std::{fmt, fs, path::Path};
+
+use super::backend::Backend;
+use super::chromium_profiles::ChromiumProfile;
+use super::firefox_profiles::FirefoxProfile;
+use super::profiles::Family;
+use super::spec::{BrowserSpec, ContainerRef, Keyring, ProfileRef};
+use super::utils::open_immutable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Works, but something may get in the way.
+    Warn,
+    Fail,
+    /// Not applicable here, or blocked by an earlier failure.
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+            Status::Skip => "skip",
+        };
+        f.pad(status)
+    }
+}
+
+/// Outcome of one step of reading cookies.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub step: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to do about a warning or failure.
+    pub remedy: Option<String>,
+}
+
+impl Check {
+    fn new(step: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            step,
+            status,
+            detail: detail.into(),
+            remedy: None,
+        }
+    }
+
+    fn remedy(mut self, remedy: impl Into<String>) -> Self {
+        self.remedy = Some(remedy.into());
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "step": self.step,
+            "status": self.status.to_string(),
+            "detail": self.detail,
+            "remedy": self.remedy,
+        })
+    }
+}
+
+/// Check every step of reading cookies from a browser profile, see [`diagnose`].
+pub fn diagnose_browser(browser: &str, profile: Option<&str>, site: Option<&str>) -> Vec<Check> {
+    let spec = BrowserSpec {
+        profile: profile.map(|profile| ProfileRef::Name(profile.to_string())),
+        ..BrowserSpec::new(browser)
+    };
+    diagnose_spec(&spec, site)
+}
+
+/// Check every step of reading cookies from a profile directory: the profile itself, the
+/// key store, the database lock, the schema, key retrieval, a sample decryption and the
+/// process elevation. With `site` the cookies of the site are read too.
+///
+/// Nothing is modified and no running browser is closed.
+pub fn diagnose(family: Family, path: &Path, site: Option<&str>) -> Vec<Check> {
+    let browser = match family {
+        Family::Gecko => "firefox",
+        Family::Chromium => "chrome",
+    };
+    let spec = BrowserSpec {
+        profile: Some(ProfileRef::Path(path.to_path_buf())),
+        ..BrowserSpec::new(browser)
+    };
+    diagnose_profile(family, path, &spec, site)
+}
+
+/// Like [`diagnose`] for the profile of a spec, reading the key from its keyring and the
+/// cookies of the site from its container.
+pub fn diagnose_spec(spec: &BrowserSpec, site: Option<&str>) -> Vec<Check> {
+    match spec.resolve() {
+        Ok((family, path)) => diagnose_profile(family, &path, spec, site),
+        Err(err) => vec![Check::new("profile", Status::Fail, err.to_string())
+            .remedy("run `gcookie profiles` to see the detected browsers and profiles")],
+    }
+}
+
+fn diagnose_profile(
+    family: Family,
+    path: &Path,
+    spec: &BrowserSpec,
+    site: Option<&str>,
+) -> Vec<Check> {
+    let mut checks = vec![];
+    let cookies_path = match family {
+        Family::Gecko => path.join("cookies.sqlite"),
+        Family::Chromium => ChromiumProfile::from_path(path).cookies_path(),
+    };
+    if !path.is_dir() {
+        checks.push(
+            Check::new(
+                "profile",
+                Status::Fail,
+                format!("{} not exists", path.display()),
+            )
+            .remedy("run `gcookie profiles` and pass an existing profile"),
+        );
+        return checks;
+    }
+    if !cookies_path.exists() {
+        checks.push(
+            Check::new(
+                "profile",
+                Status::Fail,
+                format!("{} not exists", cookies_path.display()),
+            )
+            .remedy("open the profile in the browser once, or point to the profile directory rather than `User Data`"),
+        );
+        return checks;
+    }
+    checks.push(Check::new(
+        "profile",
+        Status::Ok,
+        cookies_path.display().to_string(),
+    ));
+    if family == Family::Chromium {
+        checks.push(check_local_state(path));
+    }
+    let locked = match family {
+        Family::Gecko => FirefoxProfile::from_path(path).is_locked(),
+        Family::Chromium => ChromiumProfile::from_path(path).is_locked(),
+    };
+    checks.push(check_lock(family, locked));
+    checks.push(check_schema(family, &cookies_path));
+    if let Some(container) = &spec.container {
+        checks.push(check_container(family, path, &spec.browser, container));
+    }
+    if family == Family::Chromium {
+        checks.extend(chromium_key_checks(path, &cookies_path, spec.keyring));
+    }
+    checks.push(check_elevation());
+    if let Some(site) = site {
+        checks.push(check_site(family, spec, site, locked));
+    }
+    checks
+}
+
+fn read_local_state(path: &Path) -> Result<serde_json::Value, String> {
+    let local_state = path.join("../Local State");
+    let file = fs::File::open(&local_state)
+        .map_err(|err| format!("cannot open {}: {}", local_state.display(), err))?;
+    serde_json::from_reader(file).map_err(|err| format!("Local State is not JSON: {}", err))
+}
+
+fn check_local_state(path: &Path) -> Check {
+    let json = match read_local_state(path) {
+        Ok(json) => json,
+        Err(err) => return Check::new("local state", Status::Fail, err).remedy(
+            "pass the profile directory (`User Data/Default`), whose parent holds `Local State`",
+        ),
+    };
+    let os_crypt = &json["os_crypt"];
+    match (
+        os_crypt["encrypted_key"].is_null(),
+        os_crypt["app_bound_encrypted_key"].is_null(),
+    ) {
+        (_, false) => Check::new(
+            "local state",
+            Status::Warn,
+            "app_bound_encrypted_key present: cookies use v20 app-bound encryption",
+        )
+        .remedy("run gcookie as administrator so the app-bound key can be unwrapped"),
+        (false, true) => Check::new(
+            "local state",
+            Status::Ok,
+            "encrypted_key present: cookies use v10 encryption",
+        ),
+        (true, true) if cfg!(target_os = "windows") => {
+            Check::new("local state", Status::Fail, "os_crypt has no encrypted_key")
+                .remedy("start the browser once so it writes its key to Local State")
+        }
+        (true, true) => Check::new(
+            "local state",
+            Status::Ok,
+            "no key in Local State: the key comes from the system keyring",
+        ),
+    }
+}
+
+fn check_lock(family: Family, locked: bool) -> Check {
+    if !locked {
+        return Check::new("lock", Status::Ok, "the profile is not in use");
+    }
+    match family {
+        Family::Chromium if cfg!(target_os = "windows") => Check::new(
+            "lock",
+            Status::Warn,
+            "the browser is running and holds the cookie database",
+        )
+        .remedy("close the browser, or run gcookie as administrator to copy the locked database"),
+        _ => Check::new(
+            "lock",
+            Status::Warn,
+            "the browser is running; the newest cookies may not be written yet",
+        )
+        .remedy("close the browser to flush its cookies to disk"),
+    }
+}
+
+fn check_schema(family: Family, cookies_path: &Path) -> Check {
+    let conn = match open_immutable(cookies_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Check::new(
+                "schema",
+                Status::Fail,
+                format!("cannot open database: {}", err),
+            )
+            .remedy("close the browser or run gcookie as administrator")
+        }
+    };
+    let version = match family {
+        Family::Gecko => conn
+            .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+            .map(|v| v.to_string()),
+        Family::Chromium => {
+            conn.query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+                row.get::<_, String>(0)
+            })
+        }
+    };
+    let table = match family {
+        Family::Gecko => "moz_cookies",
+        Family::Chromium => "cookies",
+    };
+    let count = conn.query_row(&format!("SELECT count(*) FROM {table}"), [], |row| {
+        row.get::<_, i64>(0)
+    });
+    match (version, count) {
+        (Ok(version), Ok(count)) => Check::new(
+            "schema",
+            Status::Ok,
+            format!("version {}, {} cookies", version, count),
+        ),
+        (Err(err), _) | (_, Err(err)) => Check::new(
+            "schema",
+            Status::Fail,
+            format!("unexpected database layout: {}", err),
+        )
+        .remedy("check that the path is a browser profile and the file is not corrupt"),
+    }
+}
+
+fn check_container(family: Family, path: &Path, browser: &str, container: &ContainerRef) -> Check {
+    if family != Family::Gecko {
+        return Check::new(
+            "container",
+            Status::Fail,
+            format!("{} has no containers", browser),
+        )
+        .remedy("only name a container for Firefox profiles");
+    }
+    match FirefoxProfile::from_path(path).container_id(container) {
+        Ok(id) => Check::new("container", Status::Ok, format!("userContextId {}", id)),
+        Err(err) => Check::new("container", Status::Fail, err.to_string())
+            .remedy("name a container listed in containers.json, or give its userContextId"),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn chromium_key_checks(path: &Path, cookies_path: &Path, _keyring: Option<Keyring>) -> Vec<Check> {
+    use super::Chromium;
+    use crate::windows::aes_gcm_decrypt;
+
+    let key = match Chromium::new(path.to_path_buf()).get_key() {
+        Ok(key) => key,
+        Err(err) => {
+            return vec![
+                Check::new(
+                    "key",
+                    Status::Fail,
+                    format!("cannot unprotect encrypted_key: {}", err),
+                )
+                .remedy("run gcookie as the Windows user who owns the profile"),
+                Check::new("decryption", Status::Skip, "no key"),
+            ]
+        }
+    };
+    let key_check = Check::new("key", Status::Ok, "encrypted_key unprotected by DPAPI");
+    let sample = open_immutable(cookies_path).and_then(|conn| {
+        conn.query_row(
+            "SELECT encrypted_value FROM cookies WHERE substr(encrypted_value, 1, 3) = CAST('v10' AS BLOB) LIMIT 1",
+            [],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+    });
+    let decryption = match sample {
+        Ok(blob) if blob.len() > 15 => match aes_gcm_decrypt(&blob[15..], &key, &blob[3..15]) {
+            Ok(_) => Check::new("decryption", Status::Ok, "a v10 cookie was decrypted"),
+            Err(_) => Check::new("decryption", Status::Fail, "a v10 cookie failed to decrypt")
+                .remedy("Local State and Cookies belong to different profiles or the key changed; restart the browser"),
+        },
+        Ok(_) => Check::new("decryption", Status::Fail, "a v10 cookie is truncated")
+            .remedy("the database may be corrupt; restart the browser"),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            Check::new("decryption", Status::Warn, "no v10 cookie to decrypt, cookies may use v20")
+                .remedy("run gcookie as administrator so v20 cookies can be decrypted")
+        }
+        Err(err) => Check::new("decryption", Status::Skip, format!("cannot read a sample: {}", err)),
+    };
+    vec![key_check, decryption]
+}
+
+#[cfg(not(target_os = "windows"))]
+fn chromium_key_checks(path: &Path, cookies_path: &Path, keyring: Option<Keyring>) -> Vec<Check> {
+    use super::Chromium;
+
+    let chromium = Chromium::new(path.to_path_buf()).with_keyring(keyring);
+    let store = if cfg!(target_os = "macos") {
+        "Keychain"
+    } else {
+        "keyring"
+    };
+    // only macOS reads the Keychain here; the keyring is read once, below
+    let keys = match chromium.keys() {
+        Ok(keys) => keys,
+        Err(err) => {
+            return vec![
+                Check::new("key", Status::Fail, err.to_string()).remedy(
+                    "allow gcookie to read the browser's Safe Storage item in the Keychain",
+                ),
+                Check::new("decryption", Status::Skip, "no key"),
+            ]
+        }
+    };
+    let key_check = match keys.stored() {
+        Ok(_) => Check::new(
+            "key",
+            Status::Ok,
+            format!("password read from the {}", store),
+        ),
+        Err(err) => Check::new("key", Status::Warn, err.to_string()).remedy(
+            "unlock the keyring, or name it like chrome+gnomekeyring; only v11 cookies need it",
+        ),
+    };
+    let sample = open_immutable(cookies_path).and_then(|conn| {
+        conn.query_row(
+            "SELECT encrypted_value FROM cookies WHERE substr(encrypted_value, 1, 3) IN (CAST('v10' AS BLOB), CAST('v11' AS BLOB)) LIMIT 1",
+            [],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+    });
+    let decryption = match sample {
+        Ok(blob) => {
+            let version = String::from_utf8_lossy(&blob[..3]).to_string();
+            match keys.decrypt(&blob) {
+                Ok(_) => Check::new(
+                    "decryption",
+                    Status::Ok,
+                    format!("a {} cookie was decrypted", version),
+                ),
+                Err(err) => Check::new(
+                    "decryption",
+                    Status::Fail,
+                    format!("a {} cookie failed to decrypt: {}", version, err),
+                )
+                .remedy("the keyring holds another password; name the keyring in the browser spec"),
+            }
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            Check::new("decryption", Status::Skip, "no encrypted cookie to decrypt")
+        }
+        Err(err) => Check::new(
+            "decryption",
+            Status::Skip,
+            format!("cannot read a sample: {}", err),
+        ),
+    };
+    vec![key_check, decryption]
+}
+
+#[cfg(target_os = "windows")]
+fn check_elevation() -> Check {
+    if crate::windows::is_elevated() {
+        Check::new("elevation", Status::Ok, "running as administrator")
+    } else {
+        Check::new("elevation", Status::Warn, "not running as administrator")
+            .remedy("locked databases and v20 cookies of Chrome 130+ need administrator privileges")
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn check_elevation() -> Check {
+    Check::new("elevation", Status::Skip, "not needed on this platform")
+}
+
+fn check_site(family: Family, spec: &BrowserSpec, site: &str, locked: bool) -> Check {
+    if locked && family == Family::Chromium && cfg!(target_os = "windows") {
+        // reading would ask the Restart Manager to close the browser
+        return Check::new(
+            "site",
+            Status::Skip,
+            "the browser holds the cookie database",
+        );
+    }
+    match Backend::default().load_cookies_by_spec(site, spec) {
+        Ok(cookies) if cookies.is_empty() => Check::new(
+            "site",
+            Status::Warn,
+            format!("no cookies found for {}", site),
+        )
+        .remedy("log in to the site, or run `gcookie domains --filter` to find the host it uses"),
+        Ok(cookies) => Check::new(
+            "site",
+            Status::Ok,
+            format!("{} cookies found for {}", cookies.len(), site),
+        ),
+        Err(err) => {
+            Check::new("site", Status::Fail, err.to_string()).remedy("fix the failed steps above")
+        }
+    }
+}
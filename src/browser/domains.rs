@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use super::cookie::SiteCookie;
+
+/// Cookie statistics of one host in a cookie store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DomainStats {
+    /// Host as stored, a leading dot marks a domain cookie.
+    pub host: String,
+    pub count: usize,
+    /// Total length of the cookie values in bytes.
+    pub value_size: usize,
+    /// Newest creation time as Unix seconds.
+    pub newest_creation: Option<i64>,
+    /// Newest last access time as Unix seconds.
+    pub newest_access: Option<i64>,
+    /// Earliest expiry of the persistent cookies as Unix seconds.
+    pub earliest_expiry: Option<i64>,
+    /// Number of `Secure` cookies.
+    pub secure: usize,
+    /// Number of `HttpOnly` cookies.
+    pub http_only: usize,
+}
+
+impl DomainStats {
+    pub fn secure_ratio(&self) -> f64 {
+        self.secure as f64 / self.count.max(1) as f64
+    }
+
+    pub fn http_only_ratio(&self) -> f64 {
+        self.http_only as f64 / self.count.max(1) as f64
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "host": self.host,
+            "count": self.count,
+            "value_size": self.value_size,
+            "newest_creation": self.newest_creation,
+            "newest_access": self.newest_access,
+            "earliest_expiry": self.earliest_expiry,
+            "secure_ratio": self.secure_ratio(),
+            "http_only_ratio": self.http_only_ratio(),
+        })
+    }
+}
+
+/// Group cookies by host, sorted by host name.
+pub fn domain_stats(cookies: &SiteCookie) -> Vec<DomainStats> {
+    let mut domains: BTreeMap<&str, DomainStats> = BTreeMap::new();
+    for cookie in cookies.iter() {
+        let stats = domains
+            .entry(cookie.host.as_str())
+            .or_insert_with(|| DomainStats {
+                host: cookie.host.clone(),
+                ..Default::default()
+            });
+        stats.count += 1;
+        stats.value_size += cookie.value.len();
+        stats.newest_creation = stats.newest_creation.max(cookie.creation);
+        stats.newest_access = stats.newest_access.max(cookie.last_access);
+        if let Some(expires) = cookie.expires {
+            stats.earliest_expiry = Some(stats.earliest_expiry.map_or(expires, |e| e.min(expires)));
+        }
+        stats.secure += cookie.secure as usize;
+        stats.http_only += cookie.http_only as usize;
+    }
+    domains.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::cookie::Cookie;
+
+    #[test]
+    fn domain_stats_ok() {
+        let cookie = |host: &str, value: &str, expires, last_access, secure| Cookie {
+            host: host.to_string(),
+            value: value.to_string(),
+            expires,
+            last_access,
+            secure,
+            ..Default::default()
+        };
+        let cookies: SiteCookie = vec![
+            cookie(".bing.com", "abc", Some(300), Some(10), true),
+            cookie("www.bing.com", "x", None, None, false),
+            cookie(".bing.com", "de", Some(200), Some(30), false),
+            cookie(".bing.com", "", None, Some(20), true),
+        ]
+        .into();
+        let stats = domain_stats(&cookies);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].host, ".bing.com");
+        assert_eq!(stats[0].count, 3);
+        assert_eq!(stats[0].value_size, 5);
+        assert_eq!(stats[0].newest_access, Some(30));
+        assert_eq!(stats[0].earliest_expiry, Some(200));
+        assert_eq!(stats[0].secure, 2);
+        assert_eq!(stats[1].newest_access, None);
+        assert_eq!(stats[1].earliest_expiry, None);
+    }
+}
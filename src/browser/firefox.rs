@@ -1,64 +1,384 @@
-use rusqlite::{Connection, Result as SqlResult, Row};
+use rusqlite::{params, Connection, OptionalExtension, Params, Row};
 use std::path::PathBuf;
 
-use super::cookie::{Cookie, SiteCookie};
+use super::cookie::{Cookie, SameSite, SiteCookie};
+use super::firefox_profiles::FirefoxProfile;
+use super::import::{backup_database, invalid, now, Deleted, Imported, SESSION_LIFETIME};
+use super::source::Query;
+use super::utils::open_immutable;
+use crate::{Error, Result};
 pub struct Firefox {
     profile_path: PathBuf,
+    /// Only read cookies of this `userContextId`, 0 being outside of every container.
+    container: Option<u64>,
 }
 
 impl Firefox {
     pub fn new(path: PathBuf) -> Self {
-        Self { profile_path: path }
+        Self {
+            profile_path: path,
+            container: None,
+        }
+    }
+    pub fn in_container(mut self, user_context_id: u64) -> Self {
+        self.container = Some(user_context_id);
+        self
+    }
+    pub fn get_site_cookie(&self, host: &str) -> Result<String> {
+        Ok(self.get_site_cookies(host)?.to_string())
+    }
+    pub fn get_site_cookies(&self, host: &str) -> Result<SiteCookie> {
+        self.query_cookies("where host = ?1 or host = '.' || ?1", [host])
     }
-    pub fn get_site_cookie(&self, host: &str) -> SqlResult<String> {
+    /// Every cookie in the profile.
+    pub fn get_all_cookies(&self) -> Result<SiteCookie> {
+        self.query_cookies("", params![])
+    }
+    fn query_cookies<P: Params>(&self, filter: &str, params: P) -> Result<SiteCookie> {
         let path = self.profile_path.join("cookies.sqlite");
-        let conn = Connection::open(&path).expect(&format!(
-            "invalid cookie path: {}",
-            path.display().to_string()
-        ));
-        let statement = format!(
-            "SELECT host, path, name, value FROM moz_cookies where host = '{host}' or host = '.{host}'"
-        );
+        if !path.exists() {
+            return Err(Error::NotFound(path.display().to_string()));
+        }
+        // a running Firefox keeps the database locked; read it without locking or writing
+        let conn = open_immutable(&path)?;
+        let statement = format!("SELECT host, path, name, value, expiry, isSecure, isHttpOnly, sameSite, creationTime, lastAccessed, originAttributes FROM moz_cookies {filter}");
 
         let mut stmt = conn.prepare(&statement)?;
-        let rows = stmt.query_map([], |row: &Row| {
-            Ok(Cookie {
+        let rows = stmt.query_map(params, |row: &Row| {
+            let cookie = Cookie {
                 host: row.get(0)?,
                 path: row.get(1)?,
                 name: row.get(2)?,
                 value: row.get(3)?,
-                encrypted_value: vec![],
-            })
+                expires: Some(firefox_expiry(row.get(4)?)),
+                secure: row.get(5)?,
+                http_only: row.get(6)?,
+                same_site: SameSite::from_db(row.get(7)?),
+                creation: Some(row.get::<_, i64>(8)? / 1_000_000),
+                last_access: Some(row.get::<_, i64>(9)? / 1_000_000),
+                ..Default::default()
+            };
+            Ok((cookie, user_context_id(&row.get::<_, String>(10)?)))
         })?;
         let mut site_cookie = SiteCookie::new();
         for cookie in rows {
             if cookie.is_err() {
                 continue;
             }
-            let cookie = cookie?;
+            let (cookie, user_context_id) = cookie?;
+            if self.container.is_some_and(|id| id != user_context_id) {
+                continue;
+            }
             site_cookie.push(cookie);
         }
-        Ok(site_cookie.to_string())
+        Ok(site_cookie)
+    }
+
+    /// Write cookies into `moz_cookies`, in the container chosen with [`Firefox::in_container`],
+    /// replacing the row with the same name, host, path and `originAttributes`.
+    ///
+    /// Refuses to write while Firefox has the profile open, and backs up `cookies.sqlite`
+    /// first. Session cookies are given [`SESSION_LIFETIME`], as Firefox only stores
+    /// persistent cookies. The cookies matching any of `queries` in the container are
+    /// deleted first, in the same transaction, so that a failed write deletes nothing.
+    pub fn replace_cookies(&self, queries: &[Query], cookies: &[Cookie]) -> Result<Imported> {
+        let profile = FirefoxProfile::from_path(&self.profile_path);
+        if profile.is_locked() {
+            return Err(Error::Locked(format!(
+                "Firefox has {} open; close it before importing",
+                self.profile_path.display()
+            )));
+        }
+        let path = profile.cookies_path();
+        if !path.exists() {
+            return Err(Error::NotFound(path.display().to_string()));
+        }
+        let mut conn = Connection::open(&path)?;
+        let columns = table_columns(&conn)?;
+        if columns.is_empty() {
+            return Err(Error::Schema(format!(
+                "{} has no moz_cookies table",
+                path.display()
+            )));
+        }
+        let millis = expiry_in_millis(&conn)?;
+        let mut imported = Imported {
+            backup: Some(backup_database(&conn, &path)?),
+            ..Default::default()
+        };
+        let origin_attributes = match self.container {
+            Some(id) if id > 0 => format!("^userContextId={}", id),
+            _ => String::new(),
+        };
+        let now = now();
+        let tx = conn.transaction()?;
+        if !queries.is_empty() {
+            for (id, cookie) in self.matching_rows(&tx, queries)? {
+                tx.execute("DELETE FROM moz_cookies WHERE id = ?1", [id])?;
+                imported.deleted.push(cookie);
+            }
+        }
+        for (index, cookie) in cookies.iter().enumerate() {
+            if let Some(reason) = invalid(cookie) {
+                imported.skipped.push(reason);
+                continue;
+            }
+            let expiry = cookie.expires.unwrap_or(now + SESSION_LIFETIME);
+            let expiry = if millis { expiry * 1000 } else { expiry };
+            // creationTime orders cookies of the same name; offsets keep the input order
+            let creation = cookie.creation.unwrap_or(now) * 1_000_000 + index as i64;
+            let last_access = cookie.last_access.unwrap_or(now) * 1_000_000;
+            let same_site = firefox_same_site(cookie.same_site);
+            let key = params![cookie.name, cookie.host, cookie.path, origin_attributes];
+            let id = tx
+                .query_row(
+                    "SELECT id FROM moz_cookies WHERE name = ?1 AND host = ?2 AND path = ?3 AND originAttributes = ?4",
+                    key,
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?;
+            let id = match id {
+                Some(id) => {
+                    tx.execute(
+                        "UPDATE moz_cookies SET value = ?2, expiry = ?3, lastAccessed = ?4, creationTime = ?5, isSecure = ?6, isHttpOnly = ?7, sameSite = ?8 WHERE id = ?1",
+                        params![id, cookie.value, expiry, last_access, creation, cookie.secure, cookie.http_only, same_site],
+                    )?;
+                    imported.updated += 1;
+                    id
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO moz_cookies (originAttributes, name, value, host, path, expiry, lastAccessed, creationTime, isSecure, isHttpOnly, sameSite) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params![origin_attributes, cookie.name, cookie.value, cookie.host, cookie.path, expiry, last_access, creation, cookie.secure, cookie.http_only, same_site],
+                    )?;
+                    imported.inserted += 1;
+                    tx.last_insert_rowid()
+                }
+            };
+            // columns of newer schemas
+            if columns.iter().any(|c| c == "rawSameSite") {
+                tx.execute(
+                    "UPDATE moz_cookies SET rawSameSite = ?2 WHERE id = ?1",
+                    params![id, same_site],
+                )?;
+            }
+            if columns.iter().any(|c| c == "schemeMap") {
+                // bits of the schemes that may use the cookie: 1 for http, 2 for https
+                let scheme_map = if cookie.secure { 2 } else { 3 };
+                tx.execute(
+                    "UPDATE moz_cookies SET schemeMap = ?2 WHERE id = ?1",
+                    params![id, scheme_map],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(imported)
+    }
+
+    /// Delete the cookies matching any of the queries, in the container chosen with
+    /// [`Firefox::in_container`] or in every container; only list them with `dry_run`.
+    ///
+    /// Refuses to write while Firefox has the profile open, and backs up `cookies.sqlite`
+    /// before deleting anything.
+    pub fn delete_cookies(&self, queries: &[Query], dry_run: bool) -> Result<Deleted> {
+        let profile = FirefoxProfile::from_path(&self.profile_path);
+        if !dry_run && profile.is_locked() {
+            return Err(Error::Locked(format!(
+                "Firefox has {} open; close it before deleting cookies",
+                self.profile_path.display()
+            )));
+        }
+        let path = profile.cookies_path();
+        if !path.exists() {
+            return Err(Error::NotFound(path.display().to_string()));
+        }
+        let mut conn = if dry_run {
+            open_immutable(&path)?
+        } else {
+            Connection::open(&path)?
+        };
+        let rows = self.matching_rows(&conn, queries)?;
+        let mut deleted = Deleted::default();
+        if !dry_run && !rows.is_empty() {
+            deleted.backup = Some(backup_database(&conn, &path)?);
+            let tx = conn.transaction()?;
+            for (id, _) in &rows {
+                tx.execute("DELETE FROM moz_cookies WHERE id = ?1", [id])?;
+            }
+            tx.commit()?;
+        }
+        deleted.cookies = rows.into_iter().map(|(_, cookie)| cookie).collect();
+        Ok(deleted)
+    }
+
+    /// The `id`, host, path and name of the rows matching any of the queries in the container.
+    fn matching_rows(&self, conn: &Connection, queries: &[Query]) -> Result<Vec<(i64, Cookie)>> {
+        let mut stmt =
+            conn.prepare("SELECT id, host, path, name, originAttributes FROM moz_cookies")?;
+        let found = stmt.query_map([], |row| {
+            let cookie = Cookie {
+                host: row.get(1)?,
+                path: row.get(2)?,
+                name: row.get(3)?,
+                ..Default::default()
+            };
+            Ok((
+                row.get::<_, i64>(0)?,
+                cookie,
+                user_context_id(&row.get::<_, String>(4)?),
+            ))
+        })?;
+        Ok(found
+            .flatten()
+            .filter(|(_, cookie, user_context_id)| {
+                queries.iter().any(|query| query.matches(cookie))
+                    && self.container.is_none_or(|c| c == *user_context_id)
+            })
+            .map(|(id, cookie, _)| (id, cookie))
+            .collect())
+    }
+}
+
+fn table_columns(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('moz_cookies')")?;
+    let columns = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+/// Whether `expiry` holds milliseconds, like in schema 16 and later, rather than seconds.
+fn expiry_in_millis(conn: &Connection) -> Result<bool> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let max: Option<i64> =
+        conn.query_row("SELECT max(expiry) FROM moz_cookies", [], |row| row.get(0))?;
+    Ok(match max {
+        Some(max) => max > 100_000_000_000,
+        None => version >= 16,
+    })
+}
+
+/// The `sameSite` column: `0` for None, `1` for Lax, `2` for Strict. A missing attribute is
+/// stored as None, as Firefox does without `network.cookie.sameSite.laxByDefault`.
+fn firefox_same_site(same_site: SameSite) -> i64 {
+    match same_site {
+        SameSite::Unspecified | SameSite::None => 0,
+        SameSite::Lax => 1,
+        SameSite::Strict => 2,
+    }
+}
+
+/// The `userContextId` of an `originAttributes` suffix such as `^userContextId=2&firstPartyDomain=x`.
+fn user_context_id(origin_attributes: &str) -> u64 {
+    origin_attributes
+        .trim_start_matches('^')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("userContextId="))
+        .and_then(|id| id.parse().ok())
+        .unwrap_or(0)
+}
+
+/// `expiry` used to be seconds, newer schemas store milliseconds.
+fn firefox_expiry(expiry: i64) -> i64 {
+    if expiry > 100_000_000_000 {
+        expiry / 1000
+    } else {
+        expiry
     }
 }
 
 #[cfg(target_os = "windows")]
 #[test]
 fn firefox_connect_sql_ok() {
-    use std::fs;
-    let profiles_path = dirs::home_dir()
-        .unwrap()
-        .join("AppData/Roaming/Mozilla/Firefox/Profiles/");
-    let mut profile = profiles_path.clone();
-    for (i, p) in fs::read_dir(profiles_path).unwrap().enumerate() {
-        if i == 0 {
-            profile = p.unwrap().path();
-            break;
-        }
-    }
-    let firefox = Firefox {
-        profile_path: profile,
-    };
+    use super::firefox_profiles::{resolve_profile, FirefoxChannel};
+    let profile = resolve_profile(FirefoxChannel::Release, None).unwrap();
+    let firefox = Firefox::new(profile.path);
     let res = firefox.get_site_cookie("bing.com");
     assert!(res.is_ok());
 }
+
+#[test]
+fn import_cookies_ok() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().to_path_buf();
+    let conn = Connection::open(dir.join("cookies.sqlite")).unwrap();
+    conn.execute_batch("CREATE TABLE moz_cookies (id INTEGER PRIMARY KEY, originAttributes TEXT NOT NULL DEFAULT '', name TEXT, value TEXT, host TEXT, path TEXT, expiry INTEGER, lastAccessed INTEGER, creationTime INTEGER, isSecure INTEGER, isHttpOnly INTEGER, inBrowserElement INTEGER DEFAULT 0, sameSite INTEGER DEFAULT 0, rawSameSite INTEGER DEFAULT 0, schemeMap INTEGER DEFAULT 0, isPartitionedAttributeSet INTEGER DEFAULT 0, CONSTRAINT moz_uniqueid UNIQUE (name, host, path, originAttributes));
+        INSERT INTO moz_cookies (name, value, host, path, expiry, lastAccessed, creationTime, isSecure, isHttpOnly) VALUES ('sid', 'old', '.bing.com', '/', 1792479583, 0, 0, 1, 1);").unwrap();
+    drop(conn);
+    let cookie = |host: &str, value: &str| Cookie {
+        host: host.into(),
+        path: "/".into(),
+        name: "sid".into(),
+        value: value.into(),
+        expires: Some(1_800_000_000),
+        same_site: SameSite::Strict,
+        ..Default::default()
+    };
+    let cookies = [
+        cookie(".bing.com", "new"),
+        cookie("www.bing.com", "www"),
+        cookie("", "x"),
+    ];
+    let imported = Firefox::new(dir.clone())
+        .replace_cookies(&[], &cookies)
+        .unwrap();
+    assert_eq!(
+        (imported.inserted, imported.updated, imported.skipped.len()),
+        (1, 1, 1)
+    );
+    assert!(imported.backup.unwrap().exists());
+    Firefox::new(dir.clone())
+        .in_container(2)
+        .replace_cookies(&[], &cookies[..1])
+        .unwrap();
+
+    let firefox = Firefox::new(dir.clone()).in_container(0);
+    let read = firefox.get_site_cookies("bing.com").unwrap();
+    assert_eq!(read.to_string(), "sid=new");
+    assert_eq!(read[0].expires, Some(1_800_000_000));
+    assert_eq!(read[0].same_site, SameSite::Strict);
+    let read = Firefox::new(dir.clone())
+        .in_container(2)
+        .get_site_cookies("bing.com")
+        .unwrap();
+    assert_eq!(read.to_string(), "sid=new");
+    assert_eq!(
+        Firefox::new(dir.clone())
+            .get_site_cookie("www.bing.com")
+            .unwrap(),
+        "sid=www"
+    );
+
+    let query = Query::site("bing.com").unwrap();
+    let listed = Firefox::new(dir.clone())
+        .in_container(2)
+        .delete_cookies(std::slice::from_ref(&query), true)
+        .unwrap();
+    assert_eq!((listed.cookies.len(), listed.backup), (1, None));
+    let deleted = Firefox::new(dir.clone())
+        .delete_cookies(&[query], false)
+        .unwrap();
+    assert_eq!(deleted.cookies.len(), 2);
+    assert!(deleted.backup.is_some());
+    let firefox = Firefox::new(dir.clone());
+    assert_eq!(firefox.get_site_cookie("bing.com").unwrap(), "");
+    assert_eq!(firefox.get_site_cookie("www.bing.com").unwrap(), "sid=www");
+
+    let replaced = Firefox::new(dir.clone())
+        .in_container(0)
+        .replace_cookies(&[Query::site("www.bing.com").unwrap()], &cookies[..1])
+        .unwrap();
+    assert_eq!((replaced.deleted.len(), replaced.inserted), (1, 1));
+    assert_eq!(firefox.get_site_cookie("bing.com").unwrap(), "sid=new");
+    assert_eq!(firefox.get_site_cookie("www.bing.com").unwrap(), "");
+}
+
+#[test]
+fn user_context_id_ok() {
+    assert_eq!(user_context_id(""), 0);
+    assert_eq!(user_context_id("^userContextId=2"), 2);
+    assert_eq!(
+        user_context_id("^firstPartyDomain=a.com&userContextId=5"),
+        5
+    );
+}
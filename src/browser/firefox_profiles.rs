@@ -0,0 +1,399 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::spec::ContainerRef;
+use super::utils::{is_file_held, is_lock_link_held, parse_ini};
+use crate::{Error, Result};
+
+/// Release channel of a Firefox install.
+///
+/// Since Firefox 67 every install (release, ESR, Developer Edition, Nightly) keeps its own
+/// default profile, so the channel decides which install default is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirefoxChannel {
+    Release,
+    Esr,
+    DevEdition,
+    Nightly,
+}
+
+impl FirefoxChannel {
+    pub const ALL: [Self; 4] = [Self::Release, Self::Esr, Self::DevEdition, Self::Nightly];
+
+    /// Map a browser name like `firefox` or `firefox-esr` to its channel.
+    pub fn from_browser(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['_', ' '], "-").as_str() {
+            "firefox" => Some(Self::Release),
+            "firefox-esr" => Some(Self::Esr),
+            "firefox-dev" | "firefox-developer-edition" => Some(Self::DevEdition),
+            "firefox-nightly" => Some(Self::Nightly),
+            _ => None,
+        }
+    }
+
+    /// Browser name accepted by [`FirefoxChannel::from_browser`].
+    pub fn browser_name(&self) -> &'static str {
+        match self {
+            Self::Release => "Firefox",
+            Self::Esr => "Firefox-ESR",
+            Self::DevEdition => "Firefox-Dev",
+            Self::Nightly => "Firefox-Nightly",
+        }
+    }
+
+    /// Guess the channel from the directory name Firefox gave the profile it created.
+    fn from_profile_path(path: &Path) -> Option<Self> {
+        let dir = path.file_name()?.to_str()?.to_lowercase();
+        if dir.ends_with(".default-release") {
+            Some(Self::Release)
+        } else if dir.contains(".default-esr") {
+            Some(Self::Esr)
+        } else if dir.ends_with(".dev-edition-default") {
+            Some(Self::DevEdition)
+        } else if dir.ends_with(".default-nightly") {
+            Some(Self::Nightly)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirefoxProfile {
+    /// `Name` from `profiles.ini`, or the directory name for install defaults without an entry.
+    pub name: String,
+    pub path: PathBuf,
+    /// Marked with `Default=1`, the pre-67 way of choosing a profile.
+    pub is_default: bool,
+    /// Hashes of the installs using this profile as their default.
+    pub installs: Vec<String>,
+}
+
+impl FirefoxProfile {
+    /// A profile known only by its directory, like one passed with `-f`.
+    pub fn from_path(path: &Path) -> Self {
+        Self {
+            name: dir_name(path),
+            path: path.to_path_buf(),
+            is_default: false,
+            installs: vec![],
+        }
+    }
+
+    pub fn channel(&self) -> Option<FirefoxChannel> {
+        FirefoxChannel::from_profile_path(&self.path)
+    }
+
+    pub fn cookies_path(&self) -> PathBuf {
+        self.path.join("cookies.sqlite")
+    }
+
+    /// Whether a running Firefox has the profile open.
+    pub fn is_locked(&self) -> bool {
+        if cfg!(target_os = "windows") {
+            is_file_held(&self.path.join("parent.lock"))
+        } else {
+            is_lock_link_held(&self.path.join("lock"))
+        }
+    }
+
+    /// The `userContextId` of a container, looked up by name in `containers.json`.
+    ///
+    /// `none` is 0, the id of cookies outside of every container; built-in containers are
+    /// named by their label (`Personal`, `Work`, `Banking`, `Shopping`).
+    pub fn container_id(&self, container: &ContainerRef) -> Result<u64> {
+        let name = match container {
+            ContainerRef::None => return Ok(0),
+            ContainerRef::Name(name) => name,
+        };
+        if let Ok(id) = name.parse() {
+            return Ok(id);
+        }
+        let path = self.path.join("containers.json");
+        let file =
+            fs::File::open(&path).map_err(|_| Error::NotFound(path.display().to_string()))?;
+        let json: serde_json::Value = serde_json::from_reader(file)?;
+        let identities = json["identities"].as_array().into_iter().flatten();
+        for identity in identities {
+            let label = identity["name"].as_str().or_else(|| {
+                identity["l10nID"]
+                    .as_str()?
+                    .strip_prefix("userContext")?
+                    .strip_suffix(".label")
+            });
+            if label.is_some_and(|label| label.eq_ignore_ascii_case(name)) {
+                if let Some(id) = identity["userContextId"].as_u64() {
+                    return Ok(id);
+                }
+            }
+        }
+        Err(Error::NotFound(format!(
+            "container {} in {}",
+            name,
+            path.display()
+        )))
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self
+                .path
+                .file_name()
+                .is_some_and(|dir| dir.to_string_lossy().eq_ignore_ascii_case(name))
+            || self
+                .installs
+                .iter()
+                .any(|hash| hash.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Directories that may hold a Firefox `profiles.ini`.
+pub fn firefox_roots() -> Vec<PathBuf> {
+    let mut roots = vec![];
+    if cfg!(target_os = "windows") {
+        if let Some(dir) = dirs::config_dir() {
+            roots.push(dir.join("Mozilla/Firefox"));
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(dir) = dirs::config_dir() {
+            roots.push(dir.join("Firefox"));
+        }
+    } else if let Some(home) = dirs::home_dir() {
+        roots.push(home.join(".mozilla/firefox"));
+        // snap and flatpak keep a separate profile tree
+        roots.push(home.join("snap/firefox/common/.mozilla/firefox"));
+        roots.push(home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"));
+    }
+    roots
+}
+
+/// Read every profile listed in `root/profiles.ini`, along with the install defaults from
+/// `root/installs.ini` and the `[Install*]` sections of `profiles.ini`.
+pub fn read_profiles(root: &Path) -> std::io::Result<Vec<FirefoxProfile>> {
+    let content = fs::read_to_string(root.join("profiles.ini"))?;
+    let resolve = |path: &str, relative: bool| {
+        if relative {
+            root.join(path)
+        } else {
+            PathBuf::from(path)
+        }
+    };
+    let mut profiles: Vec<FirefoxProfile> = vec![];
+    let mut installs: Vec<(String, PathBuf)> = vec![];
+    for (section, entries) in parse_ini(&content) {
+        let get = |key: &str| {
+            entries
+                .iter()
+                .find(|(k, _)| k.as_str() == key)
+                .map(|(_, v)| v.as_str())
+        };
+        if let Some(hash) = section.strip_prefix("Install") {
+            if let Some(path) = get("Default") {
+                installs.push((hash.to_string(), resolve(path, true)));
+            }
+        } else if section.starts_with("Profile") {
+            let Some(path) = get("Path") else {
+                continue;
+            };
+            let path = resolve(path, get("IsRelative") != Some("0"));
+            profiles.push(FirefoxProfile {
+                name: get("Name").map_or_else(|| dir_name(&path), String::from),
+                path,
+                is_default: get("Default") == Some("1"),
+                installs: vec![],
+            });
+        }
+    }
+    // installs.ini is the older copy of the [Install*] sections; a missing file is fine
+    if let Ok(content) = fs::read_to_string(root.join("installs.ini")) {
+        for (hash, entries) in parse_ini(&content) {
+            if let Some((_, path)) = entries.iter().find(|(k, _)| k == "Default") {
+                if !installs.iter().any(|(h, _)| *h == hash) {
+                    installs.push((hash, resolve(path, !Path::new(path).is_absolute())));
+                }
+            }
+        }
+    }
+    for (hash, path) in installs {
+        match profiles.iter_mut().find(|p| same_path(&p.path, &path)) {
+            Some(profile) => profile.installs.push(hash),
+            None => profiles.push(FirefoxProfile {
+                name: dir_name(&path),
+                path,
+                is_default: false,
+                installs: vec![hash],
+            }),
+        }
+    }
+    Ok(profiles)
+}
+
+/// Every Firefox profile found in the known roots.
+pub fn find_profiles() -> Vec<FirefoxProfile> {
+    firefox_roots()
+        .iter()
+        .filter_map(|root| read_profiles(root).ok())
+        .flatten()
+        .collect()
+}
+
+/// Pick the profile the given channel would start with.
+///
+/// An install default of the same channel wins, then any install default whose channel is
+/// unknown, then the legacy `Default=1` profile and finally the first profile.
+pub fn default_profile_in(
+    profiles: &[FirefoxProfile],
+    channel: FirefoxChannel,
+) -> Option<&FirefoxProfile> {
+    let install_defaults = || profiles.iter().filter(|p| !p.installs.is_empty());
+    install_defaults()
+        .find(|p| p.channel() == Some(channel))
+        .or_else(|| install_defaults().find(|p| p.channel().is_none()))
+        .or_else(|| {
+            if channel == FirefoxChannel::Release {
+                profiles.iter().find(|p| p.is_default).or(profiles.first())
+            } else {
+                None
+            }
+        })
+}
+
+/// Find a profile by its `profiles.ini` name, its directory name or an install hash.
+pub fn find_profile_in<'a>(
+    profiles: &'a [FirefoxProfile],
+    name: &str,
+) -> Option<&'a FirefoxProfile> {
+    profiles.iter().find(|p| p.matches(name))
+}
+
+/// Resolve the profile directory for a Firefox channel.
+///
+/// With `profile` given the profile is looked up by name, otherwise the channel's install
+/// default is used.
+pub fn resolve_profile(channel: FirefoxChannel, profile: Option<&str>) -> Option<FirefoxProfile> {
+    let profiles = find_profiles();
+    match profile {
+        Some(name) => find_profile_in(&profiles, name),
+        None => default_profile_in(&profiles, channel),
+    }
+    .cloned()
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(profiles_ini: &str, installs_ini: Option<&str>) -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("profiles.ini"), profiles_ini).unwrap();
+        if let Some(installs_ini) = installs_ini {
+            fs::write(root.path().join("installs.ini"), installs_ini).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn read_profiles_ok() {
+        let tmp = fixture(
+            "[Install4F96D1932A9F858E]\nDefault=Profiles/b.dev-edition-default\nLocked=1\n\n\
+             [Profile0]\nName=default\nIsRelative=1\nPath=Profiles/a.default\nDefault=1\n\n\
+             [Profile1]\nName=work\nIsRelative=0\nPath=/opt/work\n\n\
+             [Profile2]\nName=dev-edition-default\nIsRelative=1\nPath=Profiles/b.dev-edition-default\n\n\
+             [General]\nStartWithLastProfile=1\nVersion=2\n",
+            Some("[308046B0AF4A39CB]\nDefault=Profiles/c.default-release\nLocked=1\n"),
+        );
+        let root = tmp.path();
+        let profiles = read_profiles(root).unwrap();
+        assert_eq!(profiles.len(), 4);
+        assert_eq!(profiles[0].path, root.join("Profiles/a.default"));
+        assert!(profiles[0].is_default);
+        assert_eq!(profiles[1].path, PathBuf::from("/opt/work"));
+        assert_eq!(profiles[2].installs, vec!["4F96D1932A9F858E".to_string()]);
+        assert_eq!(profiles[3].name, "c.default-release");
+        assert_eq!(profiles[3].installs, vec!["308046B0AF4A39CB".to_string()]);
+
+        let release = default_profile_in(&profiles, FirefoxChannel::Release).unwrap();
+        assert_eq!(release.name, "c.default-release");
+        let dev = default_profile_in(&profiles, FirefoxChannel::DevEdition).unwrap();
+        assert_eq!(dev.name, "dev-edition-default");
+        assert!(default_profile_in(&profiles, FirefoxChannel::Nightly).is_none());
+
+        assert_eq!(
+            find_profile_in(&profiles, "Work").unwrap().path,
+            PathBuf::from("/opt/work")
+        );
+        assert_eq!(
+            find_profile_in(&profiles, "a.default").unwrap().name,
+            "default"
+        );
+        assert_eq!(
+            find_profile_in(&profiles, "308046b0af4a39cb").unwrap().name,
+            "c.default-release"
+        );
+    }
+
+    #[test]
+    fn legacy_default_ok() {
+        let tmp = fixture(
+            "[Profile0]\nName=other\nIsRelative=1\nPath=x.other\n\n\
+             [Profile1]\nName=default\nIsRelative=1\nPath=y.default\nDefault=1\n",
+            None,
+        );
+        let root = tmp.path();
+        let profiles = read_profiles(root).unwrap();
+        let profile = default_profile_in(&profiles, FirefoxChannel::Release).unwrap();
+        assert_eq!(profile.name, "default");
+    }
+
+    #[test]
+    fn container_id_ok() {
+        let tmp = fixture("", None);
+        let root = tmp.path();
+        fs::write(
+            root.join("containers.json"),
+            r#"{"version": 5, "identities": [
+                {"userContextId": 1, "public": true, "l10nID": "userContextPersonal.label"},
+                {"userContextId": 6, "public": true, "name": "Dev"}]}"#,
+        )
+        .unwrap();
+        let profile = FirefoxProfile::from_path(root);
+        let id = |name: &str| profile.container_id(&ContainerRef::Name(name.into()));
+        assert_eq!(id("personal").unwrap(), 1);
+        assert_eq!(id("Dev").unwrap(), 6);
+        assert_eq!(id("3").unwrap(), 3);
+        assert!(id("Banking").is_err());
+        assert_eq!(profile.container_id(&ContainerRef::None).unwrap(), 0);
+    }
+
+    #[test]
+    fn channel_from_browser_ok() {
+        assert_eq!(
+            FirefoxChannel::from_browser("Firefox"),
+            Some(FirefoxChannel::Release)
+        );
+        assert_eq!(
+            FirefoxChannel::from_browser("firefox_esr"),
+            Some(FirefoxChannel::Esr)
+        );
+        assert_eq!(
+            FirefoxChannel::from_browser("Firefox Developer Edition"),
+            Some(FirefoxChannel::DevEdition)
+        );
+        assert_eq!(FirefoxChannel::from_browser("chrome"), None);
+    }
+}
@@ -1,37 +1,30 @@
-use super::{firefox::Firefox, utils::get_site};
-use std::{error::Error, path::PathBuf};
+use super::{chromium::Chromium, firefox::Firefox, utils::get_site};
+use crate::Result;
+use std::path::{Path, PathBuf};
 
-/// Get cookie from site by Chromium. Only working in Windows.
+/// Get cookie from site by Chromium with the native reader.
 ///
 /// # Examples
 ///
 /// ```no_run
 /// let site = "http://cn.bing.com";
 ///  
-/// let cookie =  match gcookie::gcookie_chrome(site, None, None) {
+/// let cookie =  match gcookie::browser::gcookie_utils::gcookie_chrome(site, None, None) {
 ///     Ok(cookie) => cookie,
 ///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
 /// };
 /// ```
-#[cfg(target_os = "windows")]
 pub fn gcookie_chrome(
     site: &str,
     browser: Option<&str>,
-    chrome_path: Option<&PathBuf>,
-) -> Result<String, Box<dyn Error>> {
-    use super::Chromium;
-
-    let os = std::env::consts::OS;
-    // if !(os == "windows" || os == "linux") {
-    if os != "windows" {
-        panic!("Chrome not supported in {}", os);
-    }
+    chrome_path: Option<&Path>,
+) -> Result<String> {
     let site = get_site(site)?;
     let chromium = match chrome_path {
-        Some(path) => Chromium::new(PathBuf::from(path)),
-        None => browser.unwrap_or("Chrome").into(),
+        Some(path) => Chromium::new(path.to_path_buf()),
+        None => Chromium::try_from(browser.unwrap_or("Chrome"))?,
     };
-    Ok(chromium.get_site_cookie(&site)?)
+    chromium.get_site_cookie(&site)
 }
 
 /// Get cookie from site by Firefox.
@@ -39,23 +32,25 @@ pub fn gcookie_chrome(
 /// # Examples
 ///
 /// ```no_run
+/// use std::path::PathBuf;
+///
 /// let site = "http://cn.bing.com";
 ///
 /// let mut path = PathBuf::new();
 /// path.push(r"C:\my_firefox\profile");
 ///
-/// let cookie =  match gcookie::gcookie_firefox(site, &path) {
+/// let cookie =  match gcookie::browser::gcookie_utils::gcookie_firefox(site, &path) {
 ///     Ok(cookie) => cookie,
 ///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
 /// };
 /// ```
-pub fn gcookie_firefox(site: &str, path: &PathBuf) -> Result<String, Box<dyn Error>> {
+pub fn gcookie_firefox(site: &str, path: &Path) -> Result<String> {
     let site = get_site(site)?;
     let firefox = Firefox::new(PathBuf::from(path));
-    Ok(firefox.get_site_cookie(&site)?)
+    firefox.get_site_cookie(&site)
 }
 
-#[cfg(test)]
+#[cfg(all(test, target_os = "windows"))]
 mod tests {
     use super::*;
 
@@ -74,4 +69,4 @@ mod tests {
         let cookie = gcookie_chrome(site, browser, None);
         assert!(cookie.is_ok());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,214 @@
+//! Write cookies into a browser profile, the inverse of reading them, or delete them.
+use rusqlite::Connection;
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::chromium::Chromium;
+use super::cookie::Cookie;
+use super::firefox::Firefox;
+use super::firefox_profiles::FirefoxProfile;
+use super::profiles::Family;
+use super::source::Query;
+use super::spec::BrowserSpec;
+use crate::Result;
+
+/// Lifetime given to session cookies, which browsers do not keep on disk.
+pub const SESSION_LIFETIME: i64 = 30 * 86_400;
+
+/// What an import changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Imported {
+    /// Cookies added to the profile.
+    pub inserted: usize,
+    /// Cookies that replaced one with the same name, host, path and container.
+    pub updated: usize,
+    /// Cookies left out, with the reason.
+    pub skipped: Vec<String>,
+    /// Host, path and name of the cookies deleted first by [`replace_cookies`].
+    pub deleted: Vec<Cookie>,
+    /// Copy of the cookie database taken before it was modified.
+    pub backup: Option<PathBuf>,
+}
+
+/// What a deletion removed, or would remove in a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deleted {
+    /// Host, path and name of the matching cookies; values are not read.
+    pub cookies: Vec<Cookie>,
+    /// Copy of the cookie database taken before it was modified.
+    pub backup: Option<PathBuf>,
+}
+
+/// How importing a cookie changes a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Insert,
+    Update,
+    Unchanged,
+}
+
+impl Change {
+    pub fn name(self) -> &'static str {
+        match self {
+            Change::Insert => "add",
+            Change::Update => "update",
+            Change::Unchanged => "same",
+        }
+    }
+}
+
+/// What importing each of `cookies` would do to a profile holding `existing`, matching
+/// cookies by host, name and path like the writers do.
+///
+/// `SameSite`, which Firefox cannot store unset, and the expiry of session cookies, which
+/// are given one when written, are not compared, so a copied cookie read back from either
+/// kind of profile is [`Change::Unchanged`].
+pub fn changes(existing: &[Cookie], cookies: &[Cookie]) -> Vec<Change> {
+    cookies
+        .iter()
+        .map(|cookie| {
+            let found = existing.iter().find(|old| {
+                old.host == cookie.host && old.name == cookie.name && old.path == cookie.path
+            });
+            match found {
+                None => Change::Insert,
+                Some(old)
+                    if old.value == cookie.value
+                        && old.secure == cookie.secure
+                        && old.http_only == cookie.http_only
+                        && (cookie.expires.is_none() || old.expires == cookie.expires) =>
+                {
+                    Change::Unchanged
+                }
+                Some(_) => Change::Update,
+            }
+        })
+        .collect()
+}
+
+/// Copy a SQLite database next to itself as `<name>.<unix time>.bak` and return the copy.
+///
+/// `VACUUM INTO` writes a consistent snapshot, including changes still in the WAL file.
+pub fn backup_database(conn: &Connection, path: &Path) -> Result<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut backup = path.with_file_name(format!("{}.{}.bak", name, now()));
+    let mut n = 1;
+    while backup.exists() {
+        n += 1;
+        backup = path.with_file_name(format!("{}.{}-{}.bak", name, now(), n));
+    }
+    conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+    Ok(backup)
+}
+
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+/// Why a cookie cannot be written, if it cannot.
+pub(super) fn invalid(cookie: &Cookie) -> Option<String> {
+    if cookie.host.trim_start_matches('.').is_empty() {
+        Some(format!("{}: no domain", cookie.name))
+    } else if cookie.name.is_empty() && cookie.value.is_empty() {
+        Some(format!("{}: empty cookie", cookie.host))
+    } else {
+        None
+    }
+}
+
+/// Write cookies into the profile named by `spec`, replacing cookies with the same name,
+/// host and path.
+///
+/// A Firefox spec may name a container; cookies are written outside of every container
+/// otherwise. A Chromium spec may name the keyring holding the key on Linux. The profile
+/// must not be open in the browser, and its database is backed up before it is modified.
+pub fn import_cookies(spec: &BrowserSpec, cookies: &[Cookie]) -> Result<Imported> {
+    replace_cookies(spec, &[], cookies)
+}
+
+/// Like [`import_cookies`], first deleting the cookies matching any of `queries` in the
+/// container written to, in the same transaction: a failed write deletes nothing.
+pub fn replace_cookies(
+    spec: &BrowserSpec,
+    queries: &[Query],
+    cookies: &[Cookie],
+) -> Result<Imported> {
+    let (family, path) = spec.resolve()?;
+    match family {
+        Family::Gecko => {
+            let profile = FirefoxProfile::from_path(&path);
+            let container = match &spec.container {
+                Some(container) => profile.container_id(container)?,
+                None => 0,
+            };
+            Firefox::new(path)
+                .in_container(container)
+                .replace_cookies(queries, cookies)
+        }
+        Family::Chromium => Chromium::new(path)
+            .with_keyring(spec.keyring)
+            .replace_cookies(queries, cookies),
+    }
+}
+
+/// Delete the cookies matching any of `queries` from the profile named by `spec`, or only
+/// list them with `dry_run`.
+///
+/// Hosts match like reads: the host itself and its `.host` domain cookies. A Firefox spec
+/// may name a container; cookies of every container are deleted otherwise. The profile
+/// must not be open in the browser, and its database is backed up before it is modified.
+pub fn delete_cookies(spec: &BrowserSpec, queries: &[Query], dry_run: bool) -> Result<Deleted> {
+    let (family, path) = spec.resolve()?;
+    match family {
+        Family::Gecko => {
+            let mut firefox = Firefox::new(path.clone());
+            if let Some(container) = &spec.container {
+                firefox =
+                    firefox.in_container(FirefoxProfile::from_path(&path).container_id(container)?);
+            }
+            firefox.delete_cookies(queries, dry_run)
+        }
+        Family::Chromium => Chromium::new(path).delete_cookies(queries, dry_run),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_ok() {
+        let cookie = |name: &str, value: &str| Cookie {
+            host: ".bing.com".into(),
+            path: "/".into(),
+            name: name.into(),
+            value: value.into(),
+            expires: Some(1_800_000_000),
+            ..Default::default()
+        };
+        let existing = [cookie("a", "1"), cookie("b", "1")];
+        let mut session = cookie("a", "1");
+        session.expires = None;
+        assert_eq!(
+            changes(
+                &existing,
+                &[
+                    cookie("a", "1"),
+                    cookie("b", "2"),
+                    cookie("c", "1"),
+                    session
+                ]
+            ),
+            [
+                Change::Unchanged,
+                Change::Update,
+                Change::Insert,
+                Change::Unchanged
+            ]
+        );
+    }
+}
@@ -1,30 +1,256 @@
-use std::{error::Error, path::PathBuf};
+use crate::{Error, Result};
+use cookie::SiteCookie;
+use firefox_profiles::FirefoxChannel;
+use profiles::{Family, ProfileInfo};
+use spec::BrowserSpec;
+use std::path::Path;
 pub mod cookie;
 pub mod utils;
 
+pub mod backend;
+pub use backend::Backend;
+
+#[cfg(feature = "rookie")]
 mod rookie_utils;
-pub use rookie_utils::*;
 
+mod chromium;
+pub use chromium::Chromium;
+#[cfg(not(target_os = "windows"))]
+mod chromium_unix;
 #[cfg(target_os = "windows")]
 mod chromium_windows;
-#[cfg(target_os = "windows")]
-pub use chromium_windows::*;
 
+pub mod chromium_profiles;
+pub mod doctor;
+pub mod domains;
 mod firefox;
+pub mod firefox_profiles;
+pub mod import;
+pub mod profiles;
+pub mod source;
+pub mod spec;
 
 pub mod gcookie_utils;
 
-#[cfg(target_os = "windows")]
-pub fn gcookie_chrome_by_path(site: &str, path: &PathBuf) -> Result<String, Box<dyn Error>> {
-    let browser = Chromium::new(path.clone());
-    if browser.is_v10() {
-        return gcookie_utils::gcookie_chrome(site, None, Some(path));
-    } else {
-        return get_chrome_cookies_by_path(site, path);
+/// Get cookie from site
+///
+/// # Examples
+///
+/// ```no_run
+/// let site = "http://bing.com";
+///  
+/// let cookie =  match gcookie::get_cookies("chrome", site) {
+///     Ok(cookie) => cookie,
+///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
+/// };
+/// ```
+pub fn get_cookies(browser: &str, site: &str) -> Result<String> {
+    Ok(load_cookies(browser, site)?.to_string())
+}
+
+/// Like [`get_cookies`], but returns the cookies instead of a `Cookie` header string.
+pub fn load_cookies(browser: &str, site: &str) -> Result<SiteCookie> {
+    Backend::default().load_cookies(browser, site)
+}
+
+/// Get cookie from site by Chrome with path.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// let site = "https://google.com";
+///
+/// let mut path = PathBuf::new();
+/// path.push(r"C:\my_chrome\user data\default");
+///
+/// let cookie =  match gcookie::get_chrome_cookies_by_path(site, &path) {
+///     Ok(cookie) => cookie,
+///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
+/// };
+/// ```
+pub fn get_chrome_cookies_by_path(site: &str, path: &Path) -> Result<String> {
+    Ok(load_chrome_cookies_by_path(site, path)?.to_string())
+}
+
+/// Like [`get_chrome_cookies_by_path`], but returns the cookies instead of a `Cookie` header string.
+pub fn load_chrome_cookies_by_path(site: &str, path: &Path) -> Result<SiteCookie> {
+    Backend::default().load_chrome_by_path(site, path, None)
+}
+
+/// Get cookie from site by Firefox with path.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// let site = "https://www.mozilla.org/";
+///
+/// let mut path = PathBuf::new();
+/// path.push(r"C:\my_firefox\profile");
+///
+/// let cookie =  match gcookie::get_firefox_cookies_by_path(site, &path) {
+///     Ok(cookie) => cookie,
+///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
+/// };
+/// ```
+pub fn get_firefox_cookies_by_path(site: &str, path: &Path) -> Result<String> {
+    Ok(load_firefox_cookies_by_path(site, path)?.to_string())
+}
+
+/// Like [`get_firefox_cookies_by_path`], but returns the cookies instead of a `Cookie` header string.
+pub fn load_firefox_cookies_by_path(site: &str, path: &Path) -> Result<SiteCookie> {
+    Backend::default().load_firefox_by_path(site, path)
+}
+
+pub fn gcookie_chrome_by_path(site: &str, path: &Path) -> Result<String> {
+    Ok(load_chrome_by_path(site, path)?.to_string())
+}
+
+/// Like [`gcookie_chrome_by_path`], but returns the cookies instead of a `Cookie` header string.
+pub fn load_chrome_by_path(site: &str, path: &Path) -> Result<SiteCookie> {
+    load_chrome_cookies_by_path(site, path)
+}
+
+/// Get cookie from site by a Firefox profile.
+///
+/// `profile` is a name from `profiles.ini`, a profile directory name or an install hash.
+/// Without it the default profile of the channel named by `browser` is used.
+///
+/// # Examples
+///
+/// ```no_run
+/// let cookie = gcookie::browser::get_firefox_cookies_by_profile("bing.com", "firefox", Some("work"));
+/// ```
+pub fn get_firefox_cookies_by_profile(
+    site: &str,
+    browser: &str,
+    profile: Option<&str>,
+) -> Result<String> {
+    Ok(load_firefox_cookies_by_profile(site, browser, profile)?.to_string())
+}
+
+/// Like [`get_firefox_cookies_by_profile`], but returns the cookies instead of a `Cookie` header string.
+pub fn load_firefox_cookies_by_profile(
+    site: &str,
+    browser: &str,
+    profile: Option<&str>,
+) -> Result<SiteCookie> {
+    if FirefoxChannel::from_browser(browser).is_none() {
+        return Err(Error::UnsupportedBrowser(format!(
+            "{} is not a Firefox browser",
+            browser
+        )));
     }
+    let (_, path) = profiles::resolve_profile_path(browser, profile)?;
+    load_firefox_cookies_by_path(site, &path)
 }
 
-#[cfg(not(target_os = "windows"))]
-pub fn gcookie_chrome_by_path(site: &str, path: &PathBuf) -> Result<String, Box<dyn Error>> {
-    return get_chrome_cookies_by_path(site, path);
+/// Get cookie from site by a profile of a Chromium-family browser.
+///
+/// `profile` is a profile directory (`Profile 1`), its display name or the email of the
+/// signed-in account. Without it the `Default` profile is used.
+///
+/// # Examples
+///
+/// ```no_run
+/// let cookie = gcookie::browser::get_chrome_cookies_by_profile("bing.com", "edge", Some("alice@corp.com"));
+/// ```
+pub fn get_chrome_cookies_by_profile(
+    site: &str,
+    browser: &str,
+    profile: Option<&str>,
+) -> Result<String> {
+    Ok(load_chrome_cookies_by_profile(site, browser, profile)?.to_string())
+}
+
+/// Like [`get_chrome_cookies_by_profile`], but returns the cookies instead of a `Cookie` header string.
+pub fn load_chrome_cookies_by_profile(
+    site: &str,
+    browser: &str,
+    profile: Option<&str>,
+) -> Result<SiteCookie> {
+    let found = chromium_profiles::resolve_profile(browser, profile)?;
+    load_chrome_by_path(site, &found.path)
+}
+
+/// Get cookie from site by a `BROWSER[+KEYRING][:PROFILE][::CONTAINER]` specification,
+/// the syntax of yt-dlp's `--cookies-from-browser`.
+///
+/// # Examples
+///
+/// ```no_run
+/// let spec = "firefox:work::Shopping".parse().unwrap();
+/// let cookie = gcookie::browser::get_cookies_by_spec("bing.com", &spec);
+/// ```
+pub fn get_cookies_by_spec(site: &str, spec: &BrowserSpec) -> Result<String> {
+    Ok(load_cookies_by_spec(site, spec)?.to_string())
+}
+
+/// Like [`get_cookies_by_spec`], but returns the cookies instead of a `Cookie` header string.
+///
+/// See [`Backend::load_cookies_by_spec`].
+pub fn load_cookies_by_spec(site: &str, spec: &BrowserSpec) -> Result<SiteCookie> {
+    Backend::default().load_cookies_by_spec(site, spec)
+}
+
+/// Try `specs` in order and return the first one that finds cookies for the site.
+///
+/// See [`Backend::load_cookies_by_specs`].
+pub fn load_cookies_by_specs<'a>(
+    site: &str,
+    specs: &'a [BrowserSpec],
+) -> Result<(&'a BrowserSpec, SiteCookie)> {
+    Backend::default().load_cookies_by_specs(site, specs)
+}
+
+/// Read the site from every detected profile and return the one whose cookies were
+/// accessed most recently.
+///
+/// See [`Backend::load_freshest_cookies`].
+pub fn load_freshest_cookies(site: &str) -> Result<(Option<ProfileInfo>, SiteCookie)> {
+    Backend::default().load_freshest_cookies(site)
+}
+
+/// Every cookie of a profile, read by the native readers.
+pub fn load_all_cookies(family: Family, path: &Path) -> Result<SiteCookie> {
+    match family {
+        Family::Gecko => firefox::Firefox::new(path.to_path_buf()).get_all_cookies(),
+        Family::Chromium => Chromium::new(path.to_path_buf()).get_all_cookies(),
+    }
+}
+
+/// The cookies matching any of `queries` in the profile named by `spec`, read by the
+/// native readers from the container or with the keyring of the spec, or by the
+/// [registered](source::register) source or vault reader named like its browser.
+pub fn load_cookies_by_queries(
+    spec: &BrowserSpec,
+    queries: &[source::Query],
+) -> Result<SiteCookie> {
+    if let Some(source) = source::external(&spec.browser) {
+        let mut cookies = source
+            .open(spec.profile_text().as_deref())?
+            .cookies(&source::Query::all())?;
+        cookies.retain(|cookie| queries.iter().any(|query| query.matches(cookie)));
+        return Ok(cookies);
+    }
+    let (family, path) = spec.resolve()?;
+    let mut cookies = match family {
+        Family::Gecko => {
+            let mut firefox = firefox::Firefox::new(path.clone());
+            if let Some(container) = &spec.container {
+                firefox = firefox.in_container(
+                    firefox_profiles::FirefoxProfile::from_path(&path).container_id(container)?,
+                );
+            }
+            firefox.get_all_cookies()?
+        }
+        Family::Chromium => Chromium::new(path)
+            .with_keyring(spec.keyring)
+            .get_all_cookies()?,
+    };
+    cookies.retain(|cookie| queries.iter().any(|query| query.matches(cookie)));
+    Ok(cookies)
 }
@@ -0,0 +1,122 @@
+use std::{fmt, path::PathBuf};
+
+use super::chromium_profiles;
+use super::firefox_profiles::{self, FirefoxChannel};
+use crate::{Error, Result};
+
+/// Browser engine family, which decides the cookie database layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    Chromium,
+    Gecko,
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Family::Chromium => write!(f, "chromium"),
+            Family::Gecko => write!(f, "gecko"),
+        }
+    }
+}
+
+/// How cookie values are stored in a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Encryption {
+    /// Stored as plain text, like every Firefox cookie.
+    Plain,
+    /// Chromium `v10`: AES-GCM with a DPAPI protected key on Windows, AES-CBC with a fixed
+    /// password on Linux.
+    V10,
+    /// Chromium `v11`: AES-CBC with a password from the Linux keyring.
+    V11,
+    /// Chromium `v20`: app-bound encryption, Chrome 127+ on Windows.
+    V20,
+}
+
+impl fmt::Display for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encryption::Plain => write!(f, "plain"),
+            Encryption::V10 => write!(f, "v10"),
+            Encryption::V11 => write!(f, "v11"),
+            Encryption::V20 => write!(f, "v20"),
+        }
+    }
+}
+
+/// A browser profile found on this machine.
+#[derive(Debug, Clone)]
+pub struct ProfileInfo {
+    pub browser: String,
+    pub family: Family,
+    /// Display name of the profile.
+    pub name: String,
+    pub path: PathBuf,
+    pub cookies_path: PathBuf,
+    pub encryption: Vec<Encryption>,
+    /// Held by a running browser.
+    pub locked: bool,
+}
+
+impl ProfileInfo {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "browser": self.browser,
+            "family": self.family.to_string(),
+            "name": self.name,
+            "path": self.path.display().to_string(),
+            "cookies_path": self.cookies_path.display().to_string(),
+            "encryption": self.encryption.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            "locked": self.locked,
+        })
+    }
+}
+
+/// List the profiles of every detected Chromium-family and Firefox browser.
+pub fn list_profiles() -> Vec<ProfileInfo> {
+    let chromium = chromium_profiles::find_profiles()
+        .into_iter()
+        .map(|profile| ProfileInfo {
+            browser: profile.browser.clone(),
+            family: Family::Chromium,
+            name: profile.name.clone(),
+            cookies_path: profile.cookies_path(),
+            encryption: profile.encryption(),
+            locked: profile.is_locked(),
+            path: profile.path,
+        });
+    let firefox = firefox_profiles::find_profiles()
+        .into_iter()
+        .map(|profile| ProfileInfo {
+            browser: profile
+                .channel()
+                .unwrap_or(FirefoxChannel::Release)
+                .browser_name()
+                .to_string(),
+            family: Family::Gecko,
+            name: profile.name.clone(),
+            cookies_path: profile.cookies_path(),
+            encryption: vec![Encryption::Plain],
+            locked: profile.is_locked(),
+            path: profile.path,
+        });
+    chromium.chain(firefox).collect()
+}
+
+/// Resolve a browser name and optional profile to the profile directory.
+///
+/// Firefox channels use their install default and Chromium-family browsers use `Default`
+/// when no profile is given.
+pub fn resolve_profile_path(browser: &str, profile: Option<&str>) -> Result<(Family, PathBuf)> {
+    if let Some(channel) = FirefoxChannel::from_browser(browser) {
+        let found =
+            firefox_profiles::resolve_profile(channel, profile).ok_or_else(|| match profile {
+                Some(name) => Error::NotFound(format!("Firefox profile {}", name)),
+                None => Error::NotFound(format!("profile of {}", browser)),
+            })?;
+        return Ok((Family::Gecko, found.path));
+    }
+    let found = chromium_profiles::resolve_profile(browser, profile)?;
+    Ok((Family::Chromium, found.path))
+}
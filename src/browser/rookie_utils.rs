@@ -1,30 +1,39 @@
+use super::cookie::{Cookie, SameSite, SiteCookie};
+use super::firefox_profiles::{resolve_profile, FirefoxChannel};
 use super::utils::get_site;
-use std::{error::Error, path::PathBuf};
+use crate::{Error, Result};
+use std::path::Path;
 
-// cookies to string
-fn gen_cookies_string(cookies: Vec<rookie::enums::Cookie>) -> String {
-    let mut cookie_str = String::new();
-    cookies.iter().for_each(|cookie| {
-        cookie_str.push_str(&format!("{}={}; ", cookie.name, cookie.value));
-    });
-    cookie_str.pop();
-    cookie_str.pop();
-    cookie_str
+fn to_site_cookie(cookies: Vec<rookie::enums::Cookie>) -> SiteCookie {
+    let cookies: Vec<Cookie> = cookies
+        .into_iter()
+        .map(|cookie| Cookie {
+            host: cookie.domain,
+            path: cookie.path,
+            name: cookie.name,
+            value: cookie.value,
+            expires: cookie.expires.map(|expires| expires as i64),
+            secure: cookie.secure,
+            http_only: cookie.http_only,
+            same_site: SameSite::from_db(cookie.same_site),
+            ..Default::default()
+        })
+        .collect();
+    cookies.into()
 }
 
-/// Get cookie from site
-///
-/// # Examples
-///
-/// ```no_run
-/// let site = "http://bing.com";
-///  
-/// let cookie =  match gcookie::get_cookies("chrome", site) {
-///     Ok(cookie) => cookie,
-///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
-/// };
-/// ```
-pub fn get_cookies(browser: &str, site: &str) -> Result<String, Box<dyn Error>> {
+/// Cookies of the site in the default profile of a browser, read by rookie.
+pub fn load_cookies(browser: &str, site: &str) -> Result<SiteCookie> {
+    // prefer the install default from profiles.ini over rookie's own profile guess
+    if let Some(channel) = FirefoxChannel::from_browser(browser) {
+        match resolve_profile(channel, None) {
+            Some(profile) => return load_firefox_by_path(site, &profile.path),
+            None if channel != FirefoxChannel::Release => {
+                return Err(Error::NotFound(format!("profile of {}", browser)))
+            }
+            None => {}
+        }
+    }
     let site = get_site(site)?;
     let domains = Some(vec![site]);
     let cookies = match browser.to_lowercase().as_str() {
@@ -32,74 +41,51 @@ pub fn get_cookies(browser: &str, site: &str) -> Result<String, Box<dyn Error>>
         "chrome" => rookie::chrome(domains),
         "chromium" => rookie::chromium(domains),
         "edge" => rookie::edge(domains),
+        "brave" => rookie::brave(domains),
+        "vivaldi" => rookie::vivaldi(domains),
         _ => {
-            return Err(Box::from(format!(
-                "Unsupported browser: {}; please use firefox, chrome, chromium or edge",
+            return Err(Error::UnsupportedBrowser(format!(
+                "{}; please use firefox, chrome, chromium, edge, brave or vivaldi",
                 browser
             )))
         }
-    }?;
+    }
+    .map_err(|err| Error::Backend(err.to_string()))?;
 
-    Ok(gen_cookies_string(cookies))
+    Ok(to_site_cookie(cookies))
 }
 
-/// Get cookie from site by Chrome with path.
-///
-/// # Examples
-///
-/// ```no_run
-/// let site = "https://google.com";
-///
-/// let mut path = PathBuf::new();
-/// path.push(r"C:\my_chrome\user data\default");
-///
-/// let cookie =  match gcookie::get_chrome_cookies_by_path(site, &path) {
-///     Ok(cookie) => cookie,
-///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
-/// };
-/// ```
-pub fn get_chrome_cookies_by_path(site: &str, path: &PathBuf) -> Result<String, Box<dyn Error>> {
+/// Cookies of the site in a Chromium profile directory, read by rookie.
+pub fn load_chrome_by_path(site: &str, path: &Path) -> Result<SiteCookie> {
     let cookies_path = path.join("Network/Cookies");
     if !cookies_path.exists() {
-        panic!("{} not exists", cookies_path.display());
+        return Err(Error::NotFound(cookies_path.display().to_string()));
     }
     let key_path = path.join("../Local State");
     let site = get_site(site)?;
     let domains = Some(vec![site]);
     let cookies = rookie::any_browser(
-        cookies_path.to_str().unwrap(),
+        &cookies_path.to_string_lossy(),
         domains,
-        Some(key_path.to_str().unwrap()),
-    )?;
+        Some(&key_path.to_string_lossy()),
+    )
+    .map_err(|err| Error::Backend(err.to_string()))?;
 
-    Ok(gen_cookies_string(cookies))
+    Ok(to_site_cookie(cookies))
 }
 
-/// Get cookie from site by Firefox with path.
-///
-/// # Examples
-///
-/// ```no_run
-/// let site = "https://www.mozilla.org/";
-///
-/// let mut path = PathBuf::new();
-/// path.push(r"C:\my_firefox\profile");
-///
-/// let cookie =  match gcookie::get_firefox_cookies_by_path(site, &path) {
-///     Ok(cookie) => cookie,
-///     Err(err) => panic!("An error occurred when get cookie '{}': {}", site, err),
-/// };
-/// ```
-pub fn get_firefox_cookies_by_path(site: &str, path: &PathBuf) -> Result<String, Box<dyn Error>> {
+/// Cookies of the site in a Firefox profile directory, read by rookie.
+pub fn load_firefox_by_path(site: &str, path: &Path) -> Result<SiteCookie> {
     let site = get_site(site)?;
     let cookies_path = path.join("cookies.sqlite");
     if !cookies_path.exists() {
-        panic!("{} not exists", cookies_path.display());
+        return Err(Error::NotFound(cookies_path.display().to_string()));
     }
     let domains = Some(vec![site]);
-    let cookies = rookie::any_browser(cookies_path.to_str().unwrap(), domains, None)?;
+    let cookies = rookie::any_browser(&cookies_path.to_string_lossy(), domains, None)
+        .map_err(|err| Error::Backend(err.to_string()))?;
 
-    Ok(gen_cookies_string(cookies))
+    Ok(to_site_cookie(cookies))
 }
 
 #[cfg(test)]
@@ -111,7 +97,7 @@ mod tests {
         let site = "google.com";
         let browser = "chrome";
 
-        let cookie = get_cookies(browser, site);
+        let cookie = load_cookies(browser, site);
         assert!(cookie.is_ok());
     }
 
@@ -121,7 +107,7 @@ mod tests {
         let site = "bing.com";
         let home_dir = dirs::home_dir().unwrap();
         let profile_path = home_dir.join("AppData/Local/Microsoft/Edge/User Data/Default/");
-        let cookie = get_chrome_cookies_by_path(site, &profile_path);
+        let cookie = load_chrome_by_path(site, &profile_path);
         assert!(cookie.is_ok());
         // println!("{}", cookie.unwrap());
     }
@@ -130,18 +116,8 @@ mod tests {
     #[cfg(target_os = "windows")]
     fn rookie_firefox_windows_ok() {
         let site = "https://www.mozilla.org/";
-        use std::fs;
-        let profiles_path = dirs::home_dir()
-            .unwrap()
-            .join("AppData/Roaming/Mozilla/Firefox/Profiles/");
-        let mut profile = profiles_path.clone();
-        for (i, p) in fs::read_dir(profiles_path).unwrap().enumerate() {
-            if i == 0 {
-                profile = p.unwrap().path();
-                break;
-            }
-        }
-        let cookie = get_firefox_cookies_by_path(site, &profile);
+        let profile = resolve_profile(FirefoxChannel::Release, None).unwrap();
+        let cookie = load_firefox_by_path(site, &profile.path);
         assert!(cookie.is_ok());
     }
 }
@@ -0,0 +1,223 @@
+//! Where cookies come from, separated from how they are filtered and printed.
+//!
+//! A [`CookieSource`] opens a [`CookieStore`] by profile, which answers a [`Query`]. The
+//! Firefox and Chromium readers are stores; downstream crates [`register`] their own
+//! sources, which are then read in place of a browser of the same name wherever a browser
+//! spec is accepted. Sources do not enumerate their profiles, so they are not listed
+//! among the profiles of the installed browsers, nor searched for the freshest cookies
+//! of a site.
+//!
+//! ```
+//! use gcookie::browser::cookie::{Cookie, SiteCookie};
+//! use gcookie::browser::source::{self, CookieSource, CookieStore, Query};
+//!
+//! struct Fixture;
+//!
+//! impl CookieStore for Fixture {
+//!     fn cookies(&self, query: &Query) -> gcookie::Result<SiteCookie> {
+//!         let cookie = Cookie {
+//!             host: ".example.com".into(),
+//!             name: "sid".into(),
+//!             value: "42".into(),
+//!             ..Default::default()
+//!         };
+//!         Ok(query.filter(vec![cookie].into()))
+//!     }
+//! }
+//!
+//! impl CookieSource for Fixture {
+//!     fn name(&self) -> &str {
+//!         "fixture"
+//!     }
+//!     fn open(&self, _profile: Option<&str>) -> gcookie::Result<Box<dyn CookieStore>> {
+//!         Ok(Box::new(Fixture))
+//!     }
+//! }
+//!
+//! source::register(Fixture);
+//! assert_eq!(gcookie::get_cookies("fixture", "example.com").unwrap(), "sid=42");
+//! ```
+use std::sync::{Arc, RwLock};
+
+use super::chromium::Chromium;
+use super::cookie::{Cookie, SiteCookie};
+use super::firefox::Firefox;
+use super::utils::get_site;
+use crate::vault::{self, VaultSource};
+use crate::Result;
+
+/// Which cookies to read from a store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Host of the site; every host when `None`.
+    pub host: Option<String>,
+    /// Cookie names to keep; all of them when empty.
+    pub names: Vec<String>,
+}
+
+impl Query {
+    /// Every cookie of the store.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// The cookies of a site, given as a URL or a host.
+    pub fn site(site: &str) -> Result<Self> {
+        Ok(Self {
+            host: Some(get_site(site)?),
+            names: vec![],
+        })
+    }
+
+    pub fn with_names(mut self, names: Vec<String>) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Whether a cookie is set for the host, itself or as a `.host` domain cookie, and has
+    /// one of the names.
+    pub fn matches(&self, cookie: &Cookie) -> bool {
+        let host = self.host.as_ref().is_none_or(|host| {
+            cookie.host == *host || cookie.host.strip_prefix('.') == Some(host.as_str())
+        });
+        host && (self.names.is_empty() || self.names.contains(&cookie.name))
+    }
+
+    /// Keep the cookies matching the query, for stores that cannot filter themselves.
+    pub fn filter(&self, mut cookies: SiteCookie) -> SiteCookie {
+        cookies.retain(|cookie| self.matches(cookie));
+        cookies
+    }
+}
+
+/// An opened cookie store, like the cookie database of one profile.
+pub trait CookieStore {
+    fn cookies(&self, query: &Query) -> Result<SiteCookie>;
+}
+
+/// A kind of cookie store with named profiles: a browser, an SSO cache, a test fixture.
+pub trait CookieSource: Send + Sync {
+    /// Name used as the browser of a spec, matched case-insensitively.
+    fn name(&self) -> &str;
+
+    /// Open a profile by name, or the default profile.
+    fn open(&self, profile: Option<&str>) -> Result<Box<dyn CookieStore>>;
+}
+
+impl CookieStore for Firefox {
+    fn cookies(&self, query: &Query) -> Result<SiteCookie> {
+        let cookies = match &query.host {
+            Some(host) => self.get_site_cookies(host)?,
+            None => self.get_all_cookies()?,
+        };
+        Ok(query.filter(cookies))
+    }
+}
+
+impl CookieStore for Chromium {
+    fn cookies(&self, query: &Query) -> Result<SiteCookie> {
+        let cookies = match &query.host {
+            Some(host) => self.get_site_cookies(host)?,
+            None => self.get_all_cookies()?,
+        };
+        Ok(query.filter(cookies))
+    }
+}
+
+static REGISTERED: RwLock<Vec<Arc<dyn CookieSource>>> = RwLock::new(Vec::new());
+
+/// Add a source, replacing a registered source of the same name.
+///
+/// Registered sources take precedence over the built-in browsers, so a fixture named
+/// `chrome` stands in for Chrome.
+pub fn register(source: impl CookieSource + 'static) {
+    let mut registered = REGISTERED.write().unwrap_or_else(|err| err.into_inner());
+    registered.retain(|s| !s.name().eq_ignore_ascii_case(source.name()));
+    registered.push(Arc::new(source));
+}
+
+/// The registered source with this name; built-in browsers are not included.
+pub fn registered(name: &str) -> Option<Arc<dyn CookieSource>> {
+    let registered = REGISTERED.read().unwrap_or_else(|err| err.into_inner());
+    registered
+        .iter()
+        .find(|source| source.name().eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+/// The source read instead of a browser of this name: a registered source, or the
+/// built-in reader of [vault](crate::vault) files.
+pub fn external(name: &str) -> Option<Arc<dyn CookieSource>> {
+    registered(name).or_else(|| {
+        name.eq_ignore_ascii_case(vault::NAME)
+            .then(|| Arc::new(VaultSource) as Arc<dyn CookieSource>)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_matches_ok() {
+        let cookie = |host: &str, name: &str| Cookie {
+            host: host.into(),
+            name: name.into(),
+            ..Default::default()
+        };
+        let query = Query::site("https://bing.com/search").unwrap();
+        assert!(query.matches(&cookie("bing.com", "a")));
+        assert!(query.matches(&cookie(".bing.com", "a")));
+        assert!(!query.matches(&cookie("cn.bing.com", "a")));
+        let query = Query::all().with_names(vec!["a".into()]);
+        assert!(query.matches(&cookie("cn.bing.com", "a")));
+        assert!(!query.matches(&cookie("cn.bing.com", "b")));
+    }
+
+    struct Fixture(&'static str);
+
+    impl CookieStore for Fixture {
+        fn cookies(&self, query: &Query) -> Result<SiteCookie> {
+            let cookie = Cookie {
+                host: "example.com".into(),
+                name: "sid".into(),
+                value: self.0.into(),
+                ..Default::default()
+            };
+            Ok(query.filter(vec![cookie].into()))
+        }
+    }
+
+    impl CookieSource for Fixture {
+        fn name(&self) -> &str {
+            "test-fixture"
+        }
+        fn open(&self, _profile: Option<&str>) -> Result<Box<dyn CookieStore>> {
+            Ok(Box::new(Fixture(self.0)))
+        }
+    }
+
+    #[test]
+    fn register_source_ok() {
+        register(Fixture("1"));
+        register(Fixture("2"));
+        let source = registered("Test-Fixture").unwrap();
+        let cookies = source
+            .open(None)
+            .unwrap()
+            .cookies(&Query::site("example.com").unwrap())
+            .unwrap();
+        assert_eq!(cookies.to_string(), "sid=2");
+        assert_eq!(
+            REGISTERED
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|s| s.name() == "test-fixture")
+                .count(),
+            1
+        );
+        assert!(registered("chrome").is_none());
+        assert!(external("vault").is_some());
+    }
+}
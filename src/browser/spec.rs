@@ -0,0 +1,288 @@
+//! Browser specifications as accepted by yt-dlp's `--cookies-from-browser`:
+//! `BROWSER[+KEYRING][:PROFILE][::CONTAINER]`.
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use super::firefox_profiles::FirefoxChannel;
+use super::profiles::{resolve_profile_path, Family};
+use crate::{Error, Result};
+
+/// Keyring holding the key of Chromium cookies on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyring {
+    BasicText,
+    GnomeKeyring,
+    KWallet,
+    KWallet5,
+    KWallet6,
+}
+
+impl Keyring {
+    pub const NAMES: [&'static str; 5] = [
+        "basictext",
+        "gnomekeyring",
+        "kwallet",
+        "kwallet5",
+        "kwallet6",
+    ];
+}
+
+impl FromStr for Keyring {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "basictext" => Ok(Keyring::BasicText),
+            "gnomekeyring" => Ok(Keyring::GnomeKeyring),
+            "kwallet" => Ok(Keyring::KWallet),
+            "kwallet5" => Ok(Keyring::KWallet5),
+            "kwallet6" => Ok(Keyring::KWallet6),
+            _ => Err(Error::InvalidSpec(format!(
+                "unknown keyring {}; please use {}",
+                s,
+                Keyring::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Keyring::BasicText => "basictext",
+            Keyring::GnomeKeyring => "gnomekeyring",
+            Keyring::KWallet => "kwallet",
+            Keyring::KWallet5 => "kwallet5",
+            Keyring::KWallet6 => "kwallet6",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The `PROFILE` part of a spec: a profile name, or a path when it contains a separator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileRef {
+    Name(String),
+    Path(PathBuf),
+}
+
+impl From<&str> for ProfileRef {
+    fn from(profile: &str) -> Self {
+        if profile.contains('/') || profile.contains(std::path::MAIN_SEPARATOR) {
+            ProfileRef::Path(PathBuf::from(profile))
+        } else {
+            ProfileRef::Name(profile.to_string())
+        }
+    }
+}
+
+/// The `CONTAINER` part of a spec, only meaningful for Firefox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerRef {
+    /// `none`: only cookies outside of every container.
+    None,
+    /// A container name (`Personal`, `Work`, ...) or its `userContextId`.
+    Name(String),
+}
+
+/// A parsed `BROWSER[+KEYRING][:PROFILE][::CONTAINER]` specification.
+///
+/// # Examples
+///
+/// ```
+/// use gcookie::browser::spec::{BrowserSpec, ContainerRef, ProfileRef};
+///
+/// let spec: BrowserSpec = "firefox:work::Shopping".parse().unwrap();
+/// assert_eq!(spec.browser, "firefox");
+/// assert_eq!(spec.profile, Some(ProfileRef::Name("work".into())));
+/// assert_eq!(spec.container, Some(ContainerRef::Name("Shopping".into())));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserSpec {
+    pub browser: String,
+    pub keyring: Option<Keyring>,
+    pub profile: Option<ProfileRef>,
+    pub container: Option<ContainerRef>,
+}
+
+impl BrowserSpec {
+    pub fn new(browser: &str) -> Self {
+        Self {
+            browser: browser.to_string(),
+            keyring: None,
+            profile: None,
+            container: None,
+        }
+    }
+
+    pub fn is_firefox(&self) -> bool {
+        FirefoxChannel::from_browser(&self.browser).is_some()
+    }
+
+    pub fn profile_name(&self) -> Option<&str> {
+        match &self.profile {
+            Some(ProfileRef::Name(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The profile as written in the spec, a name or a path.
+    pub fn profile_text(&self) -> Option<String> {
+        match &self.profile {
+            Some(ProfileRef::Name(name)) => Some(name.clone()),
+            Some(ProfileRef::Path(path)) => Some(path.display().to_string()),
+            None => None,
+        }
+    }
+
+    pub fn profile_path(&self) -> Option<&Path> {
+        match &self.profile {
+            Some(ProfileRef::Path(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Parse a comma separated list of specs, like `chrome,firefox:work`.
+    pub fn parse_list(specs: &str) -> Result<Vec<Self>> {
+        specs
+            .split(',')
+            .filter(|spec| !spec.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    pub fn family(&self) -> Family {
+        if self.is_firefox() {
+            Family::Gecko
+        } else {
+            Family::Chromium
+        }
+    }
+
+    /// The profile directory, resolving a profile name like [`resolve_profile_path`].
+    pub fn resolve(&self) -> Result<(Family, PathBuf)> {
+        match self.profile_path() {
+            Some(path) => Ok((self.family(), path.to_path_buf())),
+            None => resolve_profile_path(&self.browser, self.profile_name()),
+        }
+    }
+}
+
+impl FromStr for BrowserSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (rest, container) = match s.split_once("::") {
+            Some((rest, container)) => (rest, Some(container)),
+            None => (s, None),
+        };
+        let (rest, profile) = match rest.split_once(':') {
+            Some((rest, profile)) => (rest, Some(profile)),
+            None => (rest, None),
+        };
+        let (browser, keyring) = match rest.split_once('+') {
+            Some((browser, keyring)) => (browser, Some(keyring.parse::<Keyring>()?)),
+            None => (rest, None),
+        };
+        if browser.trim().is_empty() {
+            return Err(Error::InvalidSpec(format!("{}: missing browser", s)));
+        }
+        let spec = BrowserSpec {
+            browser: browser.trim().to_string(),
+            keyring,
+            profile: profile.filter(|p| !p.is_empty()).map(ProfileRef::from),
+            container: container.filter(|c| !c.is_empty()).map(|c| {
+                if c.eq_ignore_ascii_case("none") {
+                    ContainerRef::None
+                } else {
+                    ContainerRef::Name(c.to_string())
+                }
+            }),
+        };
+        if spec.keyring.is_some() && spec.is_firefox() {
+            return Err(Error::InvalidSpec(format!(
+                "{}: keyrings are only used by Chromium browsers",
+                s
+            )));
+        }
+        if spec.container.is_some() && !spec.is_firefox() {
+            return Err(Error::InvalidSpec(format!(
+                "{}: containers are only supported by Firefox",
+                s
+            )));
+        }
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for BrowserSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.browser)?;
+        if let Some(keyring) = self.keyring {
+            write!(f, "+{}", keyring)?;
+        }
+        match &self.profile {
+            Some(ProfileRef::Name(name)) => write!(f, ":{}", name)?,
+            Some(ProfileRef::Path(path)) => write!(f, ":{}", path.display())?,
+            None => {}
+        }
+        match &self.container {
+            Some(ContainerRef::None) => write!(f, "::none"),
+            Some(ContainerRef::Name(name)) => write!(f, "::{}", name),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spec_ok() {
+        let spec: BrowserSpec = "chrome+gnomekeyring:Profile 1".parse().unwrap();
+        assert_eq!(spec.browser, "chrome");
+        assert_eq!(spec.keyring, Some(Keyring::GnomeKeyring));
+        assert_eq!(spec.profile_name(), Some("Profile 1"));
+        assert_eq!(spec.container, None);
+        assert_eq!(spec.to_string(), "chrome+gnomekeyring:Profile 1");
+
+        let spec: BrowserSpec = "firefox:/home/me/.mozilla/firefox/abc.work::none"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            spec.profile_path(),
+            Some(Path::new("/home/me/.mozilla/firefox/abc.work"))
+        );
+        assert_eq!(spec.container, Some(ContainerRef::None));
+
+        let spec: BrowserSpec = "firefox::Personal".parse().unwrap();
+        assert_eq!(spec.profile, None);
+        assert_eq!(spec.container, Some(ContainerRef::Name("Personal".into())));
+
+        assert_eq!(
+            "Edge".parse::<BrowserSpec>().unwrap(),
+            BrowserSpec::new("Edge")
+        );
+    }
+
+    #[test]
+    fn parse_list_ok() {
+        let specs = BrowserSpec::parse_list("chrome, firefox:work::none,edge").unwrap();
+        assert_eq!(specs.len(), 3);
+        assert_eq!(specs[1].profile_name(), Some("work"));
+        assert_eq!(specs[2].browser, "edge");
+        assert!(BrowserSpec::parse_list("chrome,edge::x").is_err());
+    }
+
+    #[test]
+    fn parse_spec_err() {
+        assert!("".parse::<BrowserSpec>().is_err());
+        assert!("chrome+wallet".parse::<BrowserSpec>().is_err());
+        assert!("firefox+kwallet".parse::<BrowserSpec>().is_err());
+        assert!("chrome::Work".parse::<BrowserSpec>().is_err());
+    }
+}
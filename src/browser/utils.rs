@@ -1,8 +1,283 @@
-pub fn get_site(site: &str) -> Result<String, url::ParseError> {
+use crate::Error;
+use rusqlite::{Connection, OpenFlags};
+use std::{fs, path::Path};
+
+pub fn get_site(site: &str) -> crate::Result<String> {
     if site.starts_with("http") {
-        let url_obj = url::Url::parse(&site)?;
-        Ok(url_obj.host_str().unwrap().to_string())
+        let url_obj = url::Url::parse(site)?;
+        match url_obj.host_str() {
+            Some(host) => Ok(host.to_string()),
+            None => Err(Error::InvalidSite(site.to_string())),
+        }
     } else {
         Ok(site.to_string())
     }
-}
\ No newline at end of file
+}
+
+/// Seconds between 1601-01-01, the epoch of Chromium timestamps, and the Unix epoch.
+const WINDOWS_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// Convert a Chromium timestamp (microseconds since 1601) to Unix seconds; `0` means unset.
+pub fn chromium_time_to_unix(time: i64) -> Option<i64> {
+    if time == 0 {
+        None
+    } else {
+        Some(time / 1_000_000 - WINDOWS_EPOCH_OFFSET)
+    }
+}
+
+/// Convert Unix seconds to a Chromium timestamp, the inverse of [`chromium_time_to_unix`].
+pub fn unix_to_chromium_time(time: i64) -> i64 {
+    (time + WINDOWS_EPOCH_OFFSET) * 1_000_000
+}
+
+/// Split Unix seconds into the UTC date and the seconds since midnight.
+fn civil_from_unix(time: i64) -> (i64, i64, i64, i64) {
+    let days = time.div_euclid(86_400);
+    // civil-from-days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day, time.rem_euclid(86_400))
+}
+
+/// Format Unix seconds as a UTC `YYYY-MM-DD HH:MM` string.
+pub fn format_unix_time(time: i64) -> String {
+    let (year, month, day, seconds) = civil_from_unix(time);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Format Unix seconds as an ISO 8601 UTC timestamp, `YYYY-MM-DDTHH:MM:SS.000Z`.
+pub fn format_iso_time(time: i64) -> String {
+    let (year, month, day, seconds) = civil_from_unix(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.000Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Unix seconds of a UTC date and time of day, the inverse of [`civil_from_unix`].
+fn unix_from_civil(year: i64, month: i64, day: i64, seconds: i64) -> i64 {
+    // days-from-civil, http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146_097 + doe - 719_468) * 86_400 + seconds
+}
+
+/// Parse `HH:MM[:SS]` into seconds since midnight.
+fn parse_clock(clock: &str) -> Option<i64> {
+    let mut parts = clock.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let hours = parts.next()??;
+    let minutes = parts.next()??;
+    let seconds = parts.next().unwrap_or(Some(0))?;
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Parse an ISO 8601 timestamp like `2024-02-29T12:34:56.000Z` or `2024-02-29T13:34:56+01:00`
+/// into Unix seconds; fractions of a second are dropped and a missing offset means UTC.
+pub fn parse_iso_time(time: &str) -> Option<i64> {
+    let (date, clock) = time.trim().split_once(['T', ' '])?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (clock, offset) = match clock.find(['Z', 'z', '+', '-']) {
+        Some(at) => clock.split_at(at),
+        None => (clock, ""),
+    };
+    let clock = clock.split('.').next()?;
+    let offset = match offset.strip_prefix(['+', '-']) {
+        Some(zone) => {
+            let zone = parse_clock(zone).or_else(|| parse_clock(&format!("{}:00", zone)))?;
+            if offset.starts_with('-') {
+                -zone
+            } else {
+                zone
+            }
+        }
+        None => 0,
+    };
+    Some(unix_from_civil(year, month, day, parse_clock(clock)?) - offset)
+}
+
+/// Parse an HTTP date like `Wed, 21 Oct 2015 07:28:00 GMT`, as in the `Expires` attribute,
+/// into Unix seconds.
+///
+/// Like browsers, the fields are found by their shape, so `21-Oct-15 07:28:00 GMT` parses
+/// too; two-digit years are 1970-2069.
+pub fn parse_http_time(time: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let (mut year, mut month, mut day, mut clock) = (None, None, None, None);
+    for token in time
+        .split([' ', ',', '-', '\t'])
+        .filter(|token| !token.is_empty())
+    {
+        let lower = token.to_ascii_lowercase();
+        if token.contains(':') {
+            clock = clock.or(parse_clock(token));
+        } else if let Some(index) = MONTHS.iter().position(|m| lower.starts_with(m)) {
+            month = month.or(Some(index as i64 + 1));
+        } else if let Ok(number) = token.parse::<i64>() {
+            if token.len() <= 2 && day.is_none() {
+                day = Some(number);
+            } else {
+                year = year.or(Some(number));
+            }
+        }
+    }
+    let year = match year? {
+        year @ 0..=69 => year + 2000,
+        year @ 70..=99 => year + 1900,
+        year => year,
+    };
+    Some(unix_from_civil(year, month?, day?, clock?))
+}
+
+/// Parse an INI document like Firefox's `profiles.ini` into its sections, in file order.
+///
+/// Keys appearing before the first section header are ignored, as are comments (`;` or `#`).
+pub fn parse_ini(content: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = vec![];
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push((line[1..line.len() - 1].trim().to_string(), vec![]));
+            continue;
+        }
+        if let (Some((key, value)), Some(section)) = (line.split_once('='), sections.last_mut()) {
+            section
+                .1
+                .push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    sections
+}
+
+/// Open a SQLite database read-only without taking any lock, so a database in use by a
+/// running browser can still be inspected.
+pub fn open_immutable(path: &Path) -> rusqlite::Result<Connection> {
+    let path = path
+        .canonicalize()
+        .map_err(|_| rusqlite::Error::InvalidPath(path.to_path_buf()))?;
+    let uri =
+        url::Url::from_file_path(&path).map_err(|_| rusqlite::Error::InvalidPath(path.clone()))?;
+    Connection::open_with_flags(
+        format!("{}?immutable=1", uri),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )
+}
+
+/// Check a browser lock symlink like Firefox's `lock` (`127.0.1.1:+4242`) or Chromium's
+/// `SingletonLock` (`hostname-4242`).
+///
+/// The link is considered held if it exists and, where `/proc` is available, its process
+/// is still running.
+pub fn is_lock_link_held(link: &Path) -> bool {
+    let Ok(target) = fs::read_link(link) else {
+        return false;
+    };
+    let target = target.to_string_lossy();
+    let pid = target.rsplit(['-', '+']).next().unwrap_or_default();
+    let proc = Path::new("/proc");
+    if !proc.is_dir() || pid.is_empty() || !pid.bytes().all(|b| b.is_ascii_digit()) {
+        return true;
+    }
+    proc.join(pid).exists()
+}
+
+/// Check whether another process holds `path` open exclusively, as Windows browsers do with
+/// their lock files.
+pub fn is_file_held(path: &Path) -> bool {
+    path.exists() && fs::OpenOptions::new().append(true).open(path).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_site_ok() {
+        assert_eq!(
+            get_site("https://www.bing.com/search").unwrap(),
+            "www.bing.com"
+        );
+        assert_eq!(get_site("bing.com").unwrap(), "bing.com");
+        assert!(matches!(get_site("http://"), Err(Error::InvalidSite(_))));
+    }
+
+    #[test]
+    fn format_unix_time_ok() {
+        assert_eq!(format_unix_time(0), "1970-01-01 00:00");
+        assert_eq!(format_unix_time(1_709_210_096), "2024-02-29 12:34");
+        assert_eq!(format_iso_time(1_709_210_096), "2024-02-29T12:34:56.000Z");
+        assert_eq!(
+            chromium_time_to_unix(13_355_683_696_000_000),
+            Some(1_711_210_096)
+        );
+        assert_eq!(unix_to_chromium_time(1_711_210_096), 13_355_683_696_000_000);
+    }
+
+    #[test]
+    fn parse_time_ok() {
+        assert_eq!(
+            parse_iso_time("2024-02-29T12:34:56.000Z"),
+            Some(1_709_210_096)
+        );
+        assert_eq!(
+            parse_iso_time("2024-02-29T13:34:56+01:00"),
+            Some(1_709_210_096)
+        );
+        assert_eq!(parse_iso_time("1970-01-01T00:00:00"), Some(0));
+        assert_eq!(
+            parse_http_time("Thu, 29 Feb 2024 12:34:56 GMT"),
+            Some(1_709_210_096)
+        );
+        assert_eq!(
+            parse_http_time("Thursday, 29-Feb-24 12:34:56 GMT"),
+            Some(1_709_210_096)
+        );
+        assert_eq!(parse_http_time("tomorrow"), None);
+        assert_eq!(parse_iso_time("2024-02-29"), None);
+    }
+
+    #[test]
+    fn parse_ini_ok() {
+        let ini = "ignored=1\n[General]\nStartWithLastProfile=1\n\n; comment\n[Profile0]\nName=default\nPath = Profiles/abc.default\n";
+        let sections = parse_ini(ini);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].0, "General");
+        assert_eq!(sections[1].0, "Profile0");
+        assert_eq!(
+            sections[1].1,
+            vec![
+                ("Name".to_string(), "default".to_string()),
+                ("Path".to_string(), "Profiles/abc.default".to_string())
+            ]
+        );
+    }
+}
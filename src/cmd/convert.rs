@@ -0,0 +1,37 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::format::Format;
+use std::{fs, path::PathBuf};
+
+use super::{
+    allow_empty_arg, env_args, input_args, load_input_cookies, template_arg, MyResult, Output,
+};
+
+pub fn command() -> Command {
+    Command::new("convert")
+        .about("read cookies in one format and write them in another")
+        .args(input_args())
+        .arg(
+            arg!(--to [to] "output format")
+                .value_parser(Format::NAMES.to_vec())
+                .default_value("netscape"),
+        )
+        .arg(
+            arg!(-o --output [output] "file to write, stdout if omitted")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(template_arg())
+        .args(env_args())
+        .arg(allow_empty_arg())
+        .arg(arg!([site] "keep only the cookies of this site or @preset; also the host of cookies without a domain"))
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let output = Output::new(matches, matches.get_one::<String>("to").unwrap())?;
+    let cookies = load_input_cookies(matches)?;
+    let out = output.render(&cookies)?;
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => fs::write(path, out)?,
+        None => print!("{}", out),
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use clap::{arg, ArgAction, ArgMatches, Command};
+use gcookie::browser::{
+    cookie::SiteCookie,
+    import::{changes, import_cookies},
+    spec::{BrowserSpec, ContainerRef},
+    Backend,
+};
+
+use super::{allow_empty_arg, exit, import::print_imported, print_table, MyResult};
+
+pub fn command() -> Command {
+    Command::new("copy")
+        .about("copy the cookies of sites from one browser profile into another")
+        .arg(arg!(--from <from> "browser spec to read, like chrome:Default; a comma separated list is tried in order"))
+        .arg(arg!(--to <to> "browser spec to write, like firefox:work or firefox:work::Shopping"))
+        .arg(
+            arg!(--name [name] "copy only the cookies with this name; repeat for more")
+                .action(ArgAction::Append),
+        )
+        .arg(arg!(-n --"dry-run" "show what would change without writing"))
+        .arg(allow_empty_arg())
+        .arg(arg!(<site> ... "URLs or hosts of the sites"))
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let from = BrowserSpec::parse_list(matches.get_one::<String>("from").unwrap())?;
+    let to: BrowserSpec = matches.get_one::<String>("to").unwrap().parse()?;
+    let (_, path) = to.resolve()?;
+    let names: Vec<&String> = matches.get_many("name").into_iter().flatten().collect();
+    let sites: Vec<&String> = matches.get_many("site").into_iter().flatten().collect();
+    // cookies are written outside of every container unless `to` names one
+    let mut written = to.clone();
+    if written.is_firefox() && written.container.is_none() {
+        written.container = Some(ContainerRef::None);
+    }
+
+    let mut cookies = SiteCookie::new();
+    let mut existing = SiteCookie::new();
+    for site in &sites {
+        let (_, found) = Backend::default().load_cookies_by_specs(site, &from)?;
+        cookies.merge(found);
+        if matches.get_flag("dry-run") {
+            existing.merge(Backend::Native.load_cookies_by_spec(site, &written)?);
+        }
+    }
+    if !names.is_empty() {
+        cookies.retain(|cookie| names.contains(&&cookie.name));
+    }
+    for warning in cookies.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    if cookies.is_empty() && !matches.get_flag("allow-empty") {
+        let sites: Vec<_> = sites.iter().map(|site| site.as_str()).collect();
+        return Err(Box::new(exit::NoCookies(sites.join(", "))));
+    }
+
+    if matches.get_flag("dry-run") {
+        let rows: Vec<Vec<String>> = changes(&existing, &cookies)
+            .into_iter()
+            .zip(cookies.iter())
+            .map(|(change, cookie)| {
+                vec![
+                    change.name().to_string(),
+                    cookie.host.clone(),
+                    cookie.path.clone(),
+                    cookie.name.clone(),
+                ]
+            })
+            .collect();
+        print_table(&["change", "host", "path", "name"], &rows);
+        return Ok(());
+    }
+    let imported = import_cookies(&to, &cookies)?;
+    print_imported(&path, &imported);
+    Ok(())
+}
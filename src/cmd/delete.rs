@@ -0,0 +1,91 @@
+use clap::{arg, ArgAction, ArgMatches, Command};
+use gcookie::browser::{import::delete_cookies, source::Query};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use super::{
+    allow_empty_arg, browser_args_without_backend, exit, print_table, site_arg, target, MyResult,
+};
+
+pub fn command() -> Command {
+    Command::new("delete")
+        .about("delete the cookies of a site from a browser profile")
+        // cookies are deleted natively
+        .args(browser_args_without_backend())
+        .arg(
+            arg!(--name [name] "delete only the cookies with this name; repeat for more")
+                .action(ArgAction::Append),
+        )
+        .arg(arg!(-n --"dry-run" "list the cookies that would be deleted"))
+        .arg(arg!(-y --yes "delete without asking for confirmation"))
+        .arg(allow_empty_arg())
+        .arg(site_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let mut target = target(matches)?;
+    target.names.extend(
+        matches
+            .get_many::<String>("name")
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+    // deleting goes to one profile, so a fallback chain is not tried
+    let spec = &target.specs[0];
+    let (_, path) = spec.resolve()?;
+    let queries = target
+        .sites
+        .iter()
+        .map(|site| Ok(Query::site(site)?.with_names(target.names.clone())))
+        .collect::<gcookie::Result<Vec<_>>>()?;
+
+    let found = delete_cookies(spec, &queries, true)?;
+    if found.cookies.is_empty() {
+        if matches.get_flag("allow-empty") {
+            return Ok(());
+        }
+        return Err(Box::new(exit::NoCookies(target.sites.join(", "))));
+    }
+    let rows: Vec<Vec<String>> = found
+        .cookies
+        .iter()
+        .map(|cookie| {
+            vec![
+                cookie.host.clone(),
+                cookie.path.clone(),
+                cookie.name.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["host", "path", "name"], &rows);
+    if matches.get_flag("dry-run") {
+        return Ok(());
+    }
+    if !matches.get_flag("yes") && !confirm(found.cookies.len())? {
+        eprintln!("nothing deleted");
+        return Ok(());
+    }
+
+    let deleted = delete_cookies(spec, &queries, false)?;
+    println!("{}: {} deleted", path.display(), deleted.cookies.len());
+    if let Some(backup) = &deleted.backup {
+        println!("backup: {}", backup.display());
+    }
+    Ok(())
+}
+
+/// Ask on the terminal whether to delete `count` cookies; without a terminal to ask on,
+/// `--yes` is required.
+fn confirm(count: usize) -> MyResult<bool> {
+    if !io::stdin().is_terminal() {
+        return Err("stdin is not a terminal; pass --yes to delete without confirmation".into());
+    }
+    eprint!("delete {} cookies? [y/N] ", count);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
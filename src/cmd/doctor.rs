@@ -0,0 +1,39 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::browser::doctor::{diagnose_spec, Status};
+
+use super::{browser_args_without_backend, target, MyResult};
+
+pub fn command() -> Command {
+    Command::new("doctor")
+        .about("check each step of reading cookies and suggest fixes")
+        // the checks read like the default backend, falling back to rookie
+        .args(browser_args_without_backend())
+        .arg(
+            arg!(--format [format] "output format")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
+        .arg(arg!([site] "also read the cookies of this site or the first site of a @preset"))
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let target = target(matches)?;
+    let site = target.sites.first().map(|s| s.as_str());
+    let checks = diagnose_spec(&target.specs[0], site);
+    if matches.get_one::<String>("format").unwrap() == "json" {
+        let json: Vec<_> = checks.iter().map(|c| c.to_json()).collect();
+        println!("{}", serde_json::Value::Array(json));
+    } else {
+        for check in &checks {
+            println!("[{:^4}] {:<12} {}", check.status, check.step, check.detail);
+            if let Some(remedy) = &check.remedy {
+                println!("{:20}fix: {}", "", remedy);
+            }
+        }
+    }
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        return Err(format!("{} check(s) failed", failed).into());
+    }
+    Ok(())
+}
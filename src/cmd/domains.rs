@@ -0,0 +1,118 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::browser::{
+    cookie::SiteCookie,
+    domains::{domain_stats, DomainStats},
+    load_cookies_by_queries,
+    source::Query,
+    spec::BrowserSpec,
+    utils::format_unix_time,
+};
+use std::cmp::Reverse;
+
+use super::{browser_args_without_backend, print_table, target, MyResult};
+
+pub fn command() -> Command {
+    Command::new("domains")
+        .about("list every host in a cookie store with statistics")
+        // every store is read natively
+        .args(browser_args_without_backend())
+        .arg(arg!(--filter [filter] "only hosts containing this text"))
+        .arg(
+            arg!(--sort [sort] "sort key; numbers and times sort largest first, expiry soonest first")
+                .value_parser(["host", "count", "size", "created", "accessed", "expires"])
+                .default_value("host"),
+        )
+        .arg(arg!(--reverse "reverse the sort order"))
+        .arg(
+            arg!(--limit [limit] "show at most this many hosts")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            arg!(--format [format] "output format")
+                .value_parser(["table", "json"])
+                .default_value("table"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let cookies = load_chain(&target(matches)?.specs)?;
+    for warning in cookies.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    let mut stats = domain_stats(&cookies);
+    if let Some(filter) = matches.get_one::<String>("filter") {
+        let filter = filter.to_lowercase();
+        stats.retain(|s| s.host.to_lowercase().contains(&filter));
+    }
+    sort_stats(&mut stats, matches.get_one::<String>("sort").unwrap());
+    if matches.get_flag("reverse") {
+        stats.reverse();
+    }
+    if let Some(limit) = matches.get_one::<usize>("limit") {
+        stats.truncate(*limit);
+    }
+    if matches.get_one::<String>("format").unwrap() == "json" {
+        let json: Vec<_> = stats.iter().map(|s| s.to_json()).collect();
+        println!("{}", serde_json::Value::Array(json));
+        return Ok(());
+    }
+    let time = |t: Option<i64>| t.map(format_unix_time).unwrap_or_else(|| "-".to_string());
+    let percent = |ratio: f64| format!("{:.0}%", ratio * 100.0);
+    let rows: Vec<Vec<String>> = stats
+        .iter()
+        .map(|s| {
+            vec![
+                s.host.clone(),
+                s.count.to_string(),
+                s.value_size.to_string(),
+                time(s.newest_creation),
+                time(s.newest_access),
+                time(s.earliest_expiry),
+                percent(s.secure_ratio()),
+                percent(s.http_only_ratio()),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "HOST", "COOKIES", "SIZE", "CREATED", "ACCESSED", "EXPIRES", "SECURE", "HTTPONLY",
+        ],
+        &rows,
+    );
+    Ok(())
+}
+
+/// Every cookie of the first store of a fallback chain holding any, else of the first
+/// readable one; the first error when none can be read.
+fn load_chain(specs: &[BrowserSpec]) -> gcookie::Result<SiteCookie> {
+    let mut empty = None;
+    let mut first_err = None;
+    for spec in specs {
+        match load_cookies_by_queries(spec, &[Query::all()]) {
+            Ok(cookies) if !cookies.is_empty() => return Ok(cookies),
+            Ok(cookies) => {
+                empty.get_or_insert(cookies);
+            }
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match (empty, first_err) {
+        (Some(cookies), _) => Ok(cookies),
+        (None, Some(err)) => Err(err),
+        (None, None) => Err(gcookie::Error::NotFound("browser".into())),
+    }
+}
+
+fn sort_stats(stats: &mut [DomainStats], key: &str) {
+    match key {
+        "count" => stats.sort_by_key(|s| Reverse(s.count)),
+        "size" => stats.sort_by_key(|s| Reverse(s.value_size)),
+        "created" => stats.sort_by_key(|s| Reverse(s.newest_creation)),
+        "accessed" => stats.sort_by_key(|s| Reverse(s.newest_access)),
+        // session-only hosts have no expiry and go last
+        "expires" => stats.sort_by_key(|s| (s.earliest_expiry.is_none(), s.earliest_expiry)),
+        _ => {}
+    }
+}
@@ -0,0 +1,190 @@
+use clap::ArgMatches;
+use gcookie::Error;
+use rusqlite::ErrorCode;
+use std::{error, fmt};
+
+/// Category of a failed run, which decides the exit code.
+///
+/// The codes are documented in the README; keep them stable for scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Other = 1,
+    /// Bad arguments, site or config file; clap uses the same code for usage errors.
+    Usage = 2,
+    /// The site has no cookies in the profile.
+    Empty = 3,
+    /// The browser, profile or cookie database does not exist.
+    NotFound = 4,
+    Locked = 5,
+    Unsupported = 6,
+    /// The key is unavailable or cookies cannot be decrypted.
+    Key = 7,
+}
+
+impl Failure {
+    pub fn of(err: &(dyn error::Error + 'static)) -> Self {
+        if err.is::<NoCookies>() {
+            return Failure::Empty;
+        }
+        match err.downcast_ref::<Error>() {
+            Some(
+                Error::InvalidSite(_)
+                | Error::Config(_)
+                | Error::InvalidSpec(_)
+                | Error::Template(_)
+                | Error::Parse(_)
+                | Error::Unrepresentable(_),
+            ) => Failure::Usage,
+            Some(Error::NotFound(_)) => Failure::NotFound,
+            Some(Error::Locked(_)) => Failure::Locked,
+            // the browser holds the database
+            Some(Error::Sqlite(err))
+                if matches!(
+                    err.sqlite_error_code(),
+                    Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+                ) =>
+            {
+                Failure::Locked
+            }
+            Some(Error::UnsupportedBrowser(_)) => Failure::Unsupported,
+            Some(Error::KeyUnavailable(_) | Error::DecryptFailed(_)) => Failure::Key,
+            _ => Failure::Other,
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    pub fn kind(self) -> &'static str {
+        match self {
+            Failure::Other => "error",
+            Failure::Usage => "usage",
+            Failure::Empty => "no_cookies",
+            Failure::NotFound => "not_found",
+            Failure::Locked => "locked",
+            Failure::Unsupported => "unsupported_browser",
+            Failure::Key => "key_unavailable",
+        }
+    }
+}
+
+/// No cookie of the site was found; reported unless `--allow-empty` is given.
+#[derive(Debug)]
+pub struct NoCookies(pub String);
+
+impl fmt::Display for NoCookies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no cookies found for {}", self.0)
+    }
+}
+
+impl error::Error for NoCookies {}
+
+/// Print `err` on stderr, as a JSON object when the output format of the run is `json`,
+/// and return the exit code.
+pub fn report(matches: &ArgMatches, err: &(dyn error::Error + 'static)) -> i32 {
+    let failure = Failure::of(err);
+    if output_format(matches).is_some_and(|format| format == "json") {
+        let object = serde_json::json!({
+            "error": failure.kind(),
+            "code": failure.code(),
+            "message": err.to_string(),
+        });
+        eprintln!("{}", object);
+    } else {
+        eprintln!("{}", err);
+    }
+    failure.code()
+}
+
+/// The format of the innermost subcommand: for one reading a browser, the format
+/// [`super::target`] resolves from flags, a preset, `GCOOKIE_FORMAT` or the config file;
+/// else, or when the target itself is at fault, `--format`.
+fn output_format(matches: &ArgMatches) -> Option<String> {
+    let mut matches = matches;
+    while let Some((_, sub)) = matches.subcommand() {
+        matches = sub;
+    }
+    let given = matches.try_get_one::<String>("format").ok().flatten()?;
+    let target = matches
+        .try_get_one::<String>("chrome")
+        .is_ok()
+        .then(|| super::target(matches).ok())
+        .flatten();
+    Some(match target {
+        Some(target) => target.format(matches).to_string(),
+        None => given.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Command;
+    use rusqlite::ffi;
+    use std::io;
+
+    fn sqlite(code: i32) -> Error {
+        Error::Sqlite(rusqlite::Error::SqliteFailure(ffi::Error::new(code), None))
+    }
+
+    #[test]
+    fn failure_ok() {
+        let cases = [
+            (Error::InvalidSite("x".into()), Failure::Usage),
+            (Error::Config("x".into()), Failure::Usage),
+            (Error::InvalidSpec("x".into()), Failure::Usage),
+            (Error::Template("x".into()), Failure::Usage),
+            (Error::Parse("x".into()), Failure::Usage),
+            (Error::Unrepresentable("x".into()), Failure::Usage),
+            (Error::NotFound("x".into()), Failure::NotFound),
+            (Error::Locked("x".into()), Failure::Locked),
+            (Error::UnsupportedBrowser("x".into()), Failure::Unsupported),
+            (Error::KeyUnavailable("x".into()), Failure::Key),
+            (Error::DecryptFailed("x".into()), Failure::Key),
+            (Error::Schema("x".into()), Failure::Other),
+            (Error::Backend("x".into()), Failure::Other),
+            (Error::Io(io::Error::other("x")), Failure::Other),
+            (sqlite(ffi::SQLITE_BUSY), Failure::Locked),
+            (sqlite(ffi::SQLITE_LOCKED), Failure::Locked),
+            (sqlite(ffi::SQLITE_CORRUPT), Failure::Other),
+        ];
+        for (err, failure) in cases {
+            assert_eq!(Failure::of(&err), failure, "{:?}", err);
+        }
+        assert_eq!(Failure::of(&NoCookies("bing.com".into())), Failure::Empty);
+        let boxed: Box<dyn error::Error> = "plain".into();
+        assert_eq!(Failure::of(boxed.as_ref()), Failure::Other);
+
+        let codes = [
+            (Failure::Other, 1, "error"),
+            (Failure::Usage, 2, "usage"),
+            (Failure::Empty, 3, "no_cookies"),
+            (Failure::NotFound, 4, "not_found"),
+            (Failure::Locked, 5, "locked"),
+            (Failure::Unsupported, 6, "unsupported_browser"),
+            (Failure::Key, 7, "key_unavailable"),
+        ];
+        for (failure, code, kind) in codes {
+            assert_eq!((failure.code(), failure.kind()), (code, kind));
+        }
+    }
+
+    #[test]
+    fn output_format_ok() {
+        let app = Command::new("gcookie")
+            .subcommand(crate::cmd::snapshot::command())
+            .subcommand(crate::cmd::vault::command());
+        let format = |args: &[&str]| output_format(&app.clone().get_matches_from(args));
+        assert_eq!(
+            format(&["gcookie", "snapshot", "list", "--format", "json"]).as_deref(),
+            Some("json")
+        );
+        assert_eq!(
+            format(&["gcookie", "snapshot", "list"]).as_deref(),
+            Some("table")
+        );
+        assert_eq!(format(&["gcookie", "vault", "keygen", "key"]), None);
+    }
+}
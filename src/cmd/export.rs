@@ -0,0 +1,40 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::format::Format;
+use std::{fs, path::PathBuf};
+
+use super::{
+    allow_empty_arg, auto_arg, browser_args, env_args, load_site_cookies, site_arg, target,
+    template_arg, MyResult, Output,
+};
+
+pub fn command() -> Command {
+    Command::new("export")
+        .about("write the cookies of a site to a file")
+        .args(browser_args())
+        .arg(
+            arg!(--format [format] "output format")
+                .value_parser(Format::NAMES.to_vec())
+                .default_value("netscape"),
+        )
+        .arg(
+            arg!(-o --output [output] "file to write, stdout if omitted")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(template_arg())
+        .args(env_args())
+        .arg(auto_arg())
+        .arg(allow_empty_arg())
+        .arg(site_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let target = target(matches)?;
+    let output = Output::new(matches, target.format(matches))?;
+    let cookies = load_site_cookies(matches, &target)?;
+    let out = output.render(&cookies)?;
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => fs::write(path, out)?,
+        None => print!("{}", out),
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use clap::{arg, Arg, ArgMatches, Command};
+use gcookie::format::Format;
+
+use super::{
+    allow_empty_arg, auto_arg, browser_args, env_args, load_site_cookies, site_arg, target,
+    template_arg, MyResult, Output,
+};
+
+/// Arguments of `get`, also accepted without the subcommand.
+pub fn args() -> Vec<Arg> {
+    let mut args = browser_args();
+    args.push(
+        arg!(--format [format] "output format")
+            .value_parser(Format::NAMES.to_vec())
+            .default_value("header"),
+    );
+    args.push(template_arg());
+    args.extend(env_args());
+    args.push(auto_arg());
+    args.push(allow_empty_arg());
+    args.push(site_arg());
+    args
+}
+
+pub fn command() -> Command {
+    Command::new("get")
+        .about("print the cookie string of a site")
+        .args(args())
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let target = target(matches)?;
+    let output = Output::new(matches, target.format(matches))?;
+    let cookies = load_site_cookies(matches, &target)?;
+    print!("{}", output.render(&cookies)?);
+    Ok(())
+}
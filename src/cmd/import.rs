@@ -0,0 +1,68 @@
+use clap::{arg, ArgGroup, ArgMatches, Command};
+use gcookie::browser::{
+    import::{import_cookies, Imported},
+    spec::BrowserSpec,
+};
+use std::path::Path;
+
+use super::{allow_empty_arg, input_args, load_input_cookies, MyResult};
+
+pub fn command() -> Command {
+    Command::new("import")
+        .about("write cookies into a browser profile")
+        .arg(
+            arg!(--firefox [profile] "Firefox profile to write: name, path or install hash, with ::CONTAINER to write into a container")
+                .num_args(0..=1)
+                .default_missing_value(""),
+        )
+        .arg(arg!(--chrome [browser] "Chromium browser to write, as BROWSER[+KEYRING][:PROFILE], like chrome:Work or edge"))
+        .group(
+            ArgGroup::new("into")
+                .args(["firefox", "chrome"])
+                .required(true),
+        )
+        .args(input_args())
+        .arg(
+            arg!(--source [source] "read the cookies of the site from this browser spec instead of a file")
+                .conflicts_with_all(["input", "from"])
+                .requires("site"),
+        )
+        .arg(allow_empty_arg())
+        .arg(arg!([site] "keep only the cookies of this site or @preset; also the host of cookies without a domain"))
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let spec: BrowserSpec = match matches.get_one::<String>("firefox") {
+        Some(profile) => format!("firefox:{}", profile).parse()?,
+        None => matches.get_one::<String>("chrome").unwrap().parse()?,
+    };
+    if spec.is_firefox() != matches.contains_id("firefox") {
+        return Err(gcookie::Error::InvalidSpec(format!(
+            "{}: use --firefox for Firefox browsers",
+            spec.browser
+        ))
+        .into());
+    }
+    // resolve before reading stdin, so a wrong profile fails fast
+    let (_, path) = spec.resolve()?;
+    let cookies = load_input_cookies(matches)?;
+    let imported = import_cookies(&spec, &cookies)?;
+    print_imported(&path, &imported);
+    Ok(())
+}
+
+/// Report what an import into the profile at `path` changed.
+pub fn print_imported(path: &Path, imported: &Imported) {
+    for skipped in &imported.skipped {
+        eprintln!("warning: skipped {}", skipped);
+    }
+    println!(
+        "{}: {} inserted, {} updated",
+        path.display(),
+        imported.inserted,
+        imported.updated
+    );
+    if let Some(backup) = &imported.backup {
+        println!("backup: {}", backup.display());
+    }
+}
@@ -0,0 +1,397 @@
+use clap::{arg, parser::ValueSource, Arg, ArgAction, ArgMatches};
+use gcookie::browser::{
+    cookie::SiteCookie,
+    source::Query,
+    spec::{BrowserSpec, ProfileRef},
+    utils::get_site,
+    Backend,
+};
+use gcookie::config::{Config, Preset, Selection};
+use gcookie::format::{
+    parse::{parse, InputFormat},
+    render_with,
+    template::Template,
+    EnvOptions, Format,
+};
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+pub mod convert;
+pub mod copy;
+pub mod delete;
+pub mod doctor;
+pub mod domains;
+pub mod exit;
+pub mod export;
+pub mod get;
+pub mod import;
+pub mod profiles;
+pub mod serve;
+pub mod snapshot;
+pub mod vault;
+
+pub type MyResult<T> = Result<T, Box<dyn Error>>;
+
+/// Config file argument shared by every subcommand.
+pub fn config_arg() -> Arg {
+    arg!(--config [config] "config file, default $XDG_CONFIG_HOME/gcookie/config.toml")
+        .value_parser(clap::value_parser!(PathBuf))
+        .global(true)
+}
+
+/// Arguments choosing the browser profile to read.
+pub fn browser_args() -> Vec<Arg> {
+    vec![
+        arg!(-c --chrome [chrome] "Browser's name. Chrome, Chromium, Edge, Brave, Vivaldi, Firefox, Firefox-ESR, Firefox-Dev or Firefox-Nightly is OK. Also accepts BROWSER[+KEYRING][:PROFILE][::CONTAINER] like yt-dlp, or a comma separated list tried in order.")
+            .visible_alias("browser")
+            .default_value("Chrome"),
+        arg!(chrome_path: -p --"chrome-path" [chrome_path] "the use data path of Chrome")
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with("firefox"),
+        arg!(--profile [profile] "profile of the browser: name, directory, account email (Chromium) or install hash (Firefox)")
+            .conflicts_with_all(["chrome_path", "firefox"]),
+        arg!(firefox: -f --firefox [firefox] "path of firefox profile")
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--backend [backend] "reader of the cookie databases; auto uses native and falls back to rookie [default: auto]")
+            .value_parser(Backend::NAMES),
+    ]
+}
+
+/// [`browser_args`] without `--backend`, for commands that pick the reader themselves.
+pub fn browser_args_without_backend() -> Vec<Arg> {
+    browser_args()
+        .into_iter()
+        .filter(|arg| arg.get_id() != "backend")
+        .collect()
+}
+
+pub fn site_arg() -> Arg {
+    arg!(<site> "URL of the site, host of the site or @preset from the config file")
+}
+
+/// Flag reading the site from every detected profile instead of the chosen browser.
+pub fn auto_arg() -> Arg {
+    arg!(--auto "use the profile whose cookies of the site were accessed most recently")
+        .conflicts_with_all(["chrome", "chrome_path", "profile", "firefox"])
+}
+
+/// Flag turning "no cookies found" from a failure into an empty result.
+pub fn allow_empty_arg() -> Arg {
+    arg!(--"allow-empty" "exit with 0 when the site has no cookies")
+}
+
+/// Output template overriding `--format`.
+pub fn template_arg() -> Arg {
+    arg!(--template [template] "output template like 'Cookie: {{header}}', or @file to read it from a file; see the README")
+}
+
+/// Arguments reading cookies from a file or stdin, for `convert` and `import`.
+pub fn input_args() -> Vec<Arg> {
+    vec![
+        arg!(--from [from] "input format; auto guesses it from the content")
+            .value_parser(InputFormat::NAMES.to_vec())
+            .default_value("auto"),
+        arg!(-i --input [input] "file to read, stdin if omitted or -")
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--name [name] "keep only the cookies with this name; repeat for more")
+            .action(ArgAction::Append),
+    ]
+}
+
+/// Read the cookies of [`input_args`], or of the browsers of `--source` when the command
+/// has it, keeping those of the optional site or `@preset` and of `--name`.
+///
+/// The site is also the host of input cookies without a domain. Finding no cookie is an
+/// [`exit::NoCookies`] error unless `--allow-empty` is given.
+pub fn load_input_cookies(matches: &ArgMatches) -> MyResult<SiteCookie> {
+    let (sites, mut names) = match matches.get_one::<String>("site") {
+        Some(site) => match site.strip_prefix('@') {
+            Some(name) => {
+                let config =
+                    Config::find(matches.get_one::<PathBuf>("config").map(|p| p.as_path()))?;
+                let preset = config.preset(name)?;
+                (preset.sites.clone(), preset.names.clone())
+            }
+            None => (vec![site.clone()], vec![]),
+        },
+        None => (vec![], vec![]),
+    };
+    names.extend(
+        matches
+            .get_many::<String>("name")
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+    let queries = if sites.is_empty() {
+        vec![Query::all().with_names(names)]
+    } else {
+        sites
+            .iter()
+            .map(|site| Ok(Query::site(site)?.with_names(names.clone())))
+            .collect::<gcookie::Result<Vec<_>>>()?
+    };
+
+    let source = matches.try_get_one::<String>("source").ok().flatten();
+    let mut cookies = match source {
+        Some(source) => {
+            let specs = BrowserSpec::parse_list(source)?;
+            let mut cookies = SiteCookie::new();
+            for site in &sites {
+                let (_, found) = Backend::default().load_cookies_by_specs(site, &specs)?;
+                cookies.merge(found);
+            }
+            cookies
+        }
+        None => {
+            let text = match matches.get_one::<PathBuf>("input") {
+                Some(path) if path.as_os_str() != "-" => fs::read_to_string(path)?,
+                _ => {
+                    let mut text = String::new();
+                    io::stdin().read_to_string(&mut text)?;
+                    text
+                }
+            };
+            let from: InputFormat = matches.get_one::<String>("from").unwrap().parse()?;
+            let host = sites.first().map(|site| get_site(site)).transpose()?;
+            parse(&text, from, host.as_deref())?
+        }
+    };
+    cookies.retain(|cookie| queries.iter().any(|query| query.matches(cookie)));
+    for warning in cookies.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    if cookies.is_empty() && !matches.get_flag("allow-empty") {
+        let what = if sites.is_empty() {
+            "the input".to_string()
+        } else {
+            sites.join(", ")
+        };
+        return Err(Box::new(exit::NoCookies(what)));
+    }
+    Ok(cookies)
+}
+
+/// Naming of the variables of the env, dotenv and k8s-secret formats.
+pub fn env_args() -> Vec<Arg> {
+    vec![
+        arg!(--"env-name" [name] "variable name, or prefix of one variable per cookie; also names the k8s Secret, with _ as -")
+            .default_value("COOKIE"),
+        arg!(--combined "env formats: one variable holding the whole Cookie header"),
+    ]
+}
+
+/// How `get` and `export` print cookies: `--template`, else the format of the target.
+pub enum Output {
+    Template(Template),
+    Format(Format, EnvOptions),
+}
+
+impl Output {
+    /// Parse the template, or else `format`, before any cookie is read.
+    pub fn new(matches: &ArgMatches, format: &str) -> MyResult<Self> {
+        match matches.get_one::<String>("template") {
+            Some(template) => Ok(Output::Template(Template::load(template)?)),
+            None => Ok(Output::Format(
+                format.parse()?,
+                EnvOptions {
+                    name: matches.get_one::<String>("env-name").unwrap().clone(),
+                    combined: matches.get_flag("combined"),
+                },
+            )),
+        }
+    }
+
+    pub fn render(&self, cookies: &SiteCookie) -> gcookie::Result<String> {
+        match self {
+            Output::Template(template) => Ok(template.render(cookies)),
+            Output::Format(format, env) => render_with(cookies, *format, env),
+        }
+    }
+}
+
+/// What to read: the profile and format layered from flags, a `@preset` site,
+/// `GCOOKIE_*` variables and the config file, in that order.
+pub struct Target {
+    pub selection: Selection,
+    /// The browsers of `selection`, tried in order, with its profile or path applied.
+    pub specs: Vec<BrowserSpec>,
+    /// The site argument, or the sites of its preset.
+    pub sites: Vec<String>,
+    /// Cookie names to keep; all of them when empty.
+    pub names: Vec<String>,
+    pub backend: Backend,
+}
+
+impl Target {
+    /// The output format, falling back to the default of the `--format` argument.
+    pub fn format<'a>(&'a self, matches: &'a ArgMatches) -> &'a str {
+        self.selection
+            .format
+            .as_deref()
+            .unwrap_or_else(|| matches.get_one::<String>("format").unwrap())
+    }
+}
+
+pub fn target(matches: &ArgMatches) -> MyResult<Target> {
+    let config = Config::find(matches.get_one::<PathBuf>("config").map(|p| p.as_path()))?;
+    let site = matches.try_get_one::<String>("site").ok().flatten();
+    let (preset, sites) = match site.and_then(|site| site.strip_prefix('@')) {
+        Some(name) => {
+            let preset = config.preset(name)?.clone();
+            if preset.sites.is_empty() {
+                return Err(
+                    gcookie::Error::Config(format!("preset @{} has no sites", name)).into(),
+                );
+            }
+            let sites = preset.sites.clone();
+            (preset, sites)
+        }
+        None => (Preset::default(), site.into_iter().cloned().collect()),
+    };
+    let selection = flag_selection(matches)
+        .or(preset.selection())
+        .or(Selection::from_env())
+        .or(config.selection());
+    let mut specs = BrowserSpec::parse_list(selection.browser.as_deref().unwrap_or("Chrome"))?;
+    // a profile directory belongs to one browser
+    if let (Some(path), true) = (&selection.path, specs.len() > 1) {
+        return Err(gcookie::Error::InvalidSpec(format!(
+            "{} is the profile of one browser; give a single browser with it",
+            path.display()
+        ))
+        .into());
+    }
+    // a profile or path chosen separately wins over the one in the spec
+    for spec in specs.iter_mut() {
+        if let Some(path) = &selection.path {
+            spec.profile = Some(ProfileRef::Path(path.clone()));
+        } else if let Some(profile) = &selection.profile {
+            spec.profile = Some(ProfileRef::Name(profile.clone()));
+        }
+    }
+    if specs.is_empty() {
+        return Err(gcookie::Error::InvalidSpec("no browser given".into()).into());
+    }
+    let backend = match &selection.backend {
+        Some(backend) => backend.parse()?,
+        None => Backend::default(),
+    };
+    Ok(Target {
+        selection,
+        specs,
+        sites,
+        names: preset.names,
+        backend,
+    })
+}
+
+/// The part of [`browser_args`] and `--format` given on the command line.
+fn flag_selection(matches: &ArgMatches) -> Selection {
+    let given = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let mut selection = Selection {
+        browser: matches
+            .get_one::<String>("chrome")
+            .filter(|_| given("chrome"))
+            .cloned(),
+        profile: matches.get_one::<String>("profile").cloned(),
+        path: None,
+        format: matches
+            .try_get_one::<String>("format")
+            .ok()
+            .flatten()
+            .filter(|_| given("format"))
+            .cloned(),
+        backend: matches
+            .try_get_one::<String>("backend")
+            .ok()
+            .flatten()
+            .cloned(),
+    };
+    let firefox = selection
+        .browser
+        .as_deref()
+        .and_then(|browser| BrowserSpec::parse_list(browser).ok()?.into_iter().next())
+        .map(|spec| spec.is_firefox());
+    if let Some(path) = matches.get_one::<PathBuf>("firefox") {
+        selection.path = Some(path.clone());
+        if firefox != Some(true) {
+            selection.browser = Some("Firefox".to_string());
+        }
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("chrome_path") {
+        selection.path = Some(path.clone());
+        if firefox != Some(false) {
+            selection.browser = Some("Chrome".to_string());
+        }
+    }
+    selection
+}
+
+/// Read the cookies of every site of `target`.
+///
+/// Cookies that could not be read are reported on stderr. Finding no cookie is an
+/// [`exit::NoCookies`] error unless `--allow-empty` is given.
+pub fn load_site_cookies(matches: &ArgMatches, target: &Target) -> MyResult<SiteCookie> {
+    let mut cookies = SiteCookie::new();
+    for site in &target.sites {
+        cookies.merge(read_site_cookies(matches, target, site)?);
+    }
+    if !target.names.is_empty() {
+        cookies.retain(|cookie| target.names.contains(&cookie.name));
+    }
+    for warning in cookies.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    if cookies.is_empty() && !matches.get_flag("allow-empty") {
+        return Err(Box::new(exit::NoCookies(target.sites.join(", "))));
+    }
+    Ok(cookies)
+}
+
+/// Read one site with `--auto`, the fallback chain or the single browser, reporting on
+/// stderr which source was used when there was a choice.
+fn read_site_cookies(matches: &ArgMatches, target: &Target, site: &str) -> MyResult<SiteCookie> {
+    if matches.get_flag("auto") {
+        let (profile, cookies) = target.backend.load_freshest_cookies(site)?;
+        if let Some(profile) = profile {
+            eprintln!(
+                "{}: using {} {} ({})",
+                site,
+                profile.browser,
+                profile.name,
+                profile.path.display()
+            );
+        }
+        return Ok(cookies);
+    }
+    if let [spec] = target.specs.as_slice() {
+        return Ok(target.backend.load_cookies_by_spec(site, spec)?);
+    }
+    let (spec, cookies) = target.backend.load_cookies_by_specs(site, &target.specs)?;
+    eprintln!("{}: using {}", site, spec);
+    Ok(cookies)
+}
+
+/// Print rows as left-aligned columns under a header.
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let line: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
@@ -0,0 +1,51 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::browser::profiles::{list_profiles, ProfileInfo};
+
+use super::{print_table, MyResult};
+
+pub fn command() -> Command {
+    Command::new("profiles")
+        .about("list the profiles of every detected browser")
+        .arg(
+            arg!(--format [format] "output format")
+                .value_parser(["table", "json"])
+                .default_value("table"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let format = matches.get_one::<String>("format").unwrap();
+    print_profiles(&list_profiles(), format);
+    Ok(())
+}
+
+fn print_profiles(profiles: &[ProfileInfo], format: &str) {
+    if format == "json" {
+        let json: Vec<_> = profiles.iter().map(|p| p.to_json()).collect();
+        println!("{}", serde_json::Value::Array(json));
+        return;
+    }
+    let header = [
+        "BROWSER",
+        "PROFILE",
+        "ENCRYPTION",
+        "LOCKED",
+        "PATH",
+        "COOKIES",
+    ];
+    let rows: Vec<Vec<String>> = profiles
+        .iter()
+        .map(|p| {
+            let encryption: Vec<_> = p.encryption.iter().map(|e| e.to_string()).collect();
+            vec![
+                p.browser.clone(),
+                p.name.clone(),
+                encryption.join("+"),
+                if p.locked { "yes" } else { "no" }.to_string(),
+                p.path.display().to_string(),
+                p.cookies_path.display().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&header, &rows);
+}
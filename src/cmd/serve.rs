@@ -0,0 +1,514 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::browser::{
+    chromium_profiles::ChromiumProfile, cookie::SiteCookie, firefox_profiles::FirefoxProfile,
+    profiles::Family, source, spec::BrowserSpec, Backend,
+};
+use gcookie::format::{render_with, EnvOptions, Format};
+use gcookie::vault::{self, generate_key_file};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{browser_args, exit::Failure, target, MyResult};
+
+pub fn command() -> Command {
+    Command::new("serve")
+        .about("serve cookies to local programs over HTTP")
+        .args(browser_args())
+        .arg(
+            arg!(--listen [listen] "loopback address and port to listen on")
+                .value_parser(clap::value_parser!(SocketAddr))
+                .default_value("127.0.0.1:8787"),
+        )
+        .arg(
+            arg!(--socket [socket] "also listen on this Unix socket")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(--"token-file" [token_file] "file holding the bearer token, created if missing [default: gcookie/serve.token in the config directory]")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let target = target(matches)?;
+    let addr = *matches.get_one::<SocketAddr>("listen").unwrap();
+    if !addr.ip().is_loopback() {
+        return Err(format!("{}: serve only listens on loopback addresses", addr).into());
+    }
+    let token_file = match matches.get_one::<PathBuf>("token-file") {
+        Some(path) => path.clone(),
+        None => gcookie::config::Config::default_path()
+            .ok_or("no config directory; pass --token-file")?
+            .with_file_name("serve.token"),
+    };
+    if !token_file.exists() {
+        generate_key_file(&token_file)?;
+        eprintln!("created token {}", token_file.display());
+    }
+    let token = fs::read_to_string(&token_file)?.trim().to_string();
+    if token.is_empty() {
+        return Err(format!("{}: empty token", token_file.display()).into());
+    }
+    let server = Arc::new(Server {
+        specs: target.specs.clone(),
+        backend: target.backend,
+        format: target
+            .selection
+            .format
+            .clone()
+            .unwrap_or_else(|| "header".to_string()),
+        token,
+        cache: Mutex::new(HashMap::new()),
+    });
+
+    let pool = Pool::new(WORKERS);
+    if let Some(path) = matches.get_one::<PathBuf>("socket") {
+        serve_socket(path, Arc::clone(&server), pool.clone())?;
+    }
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("listening on http://{}", addr);
+    for stream in listener.incoming().flatten() {
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let server = Arc::clone(&server);
+        pool.execute(move || server.handle(stream));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn serve_socket(path: &Path, server: Arc<Server>, pool: Pool) -> MyResult<()> {
+    use std::os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::UnixListener,
+    };
+    // a socket left by a previous run; never remove anything else
+    if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    eprintln!("listening on {}", path.display());
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+            let server = Arc::clone(&server);
+            pool.execute(move || server.handle(stream));
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_path: &Path, _server: Arc<Server>, _pool: Pool) -> MyResult<()> {
+    Err("Unix sockets are not supported on this platform".into())
+}
+
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections answered at once; more wait in a queue of the same size, then in the
+/// listen backlog.
+const WORKERS: usize = 8;
+/// Sites kept in the cache; the least recently used one is dropped first.
+const MAX_CACHED: usize = 64;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of threads answering connections.
+#[derive(Clone)]
+struct Pool {
+    sender: mpsc::SyncSender<Job>,
+}
+
+impl Pool {
+    fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..size {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let job = receiver
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .recv();
+                match job {
+                    Ok(job) => job(),
+                    Err(_) => break,
+                }
+            });
+        }
+        Self { sender }
+    }
+
+    /// Queue a job, waiting while the queue is full.
+    fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.sender.send(Box::new(job));
+    }
+}
+
+/// Modification time and size of a file, `None` when it does not exist.
+type Stamp = Option<(SystemTime, u64)>;
+
+/// The cookies read for a site, with the stamps of the files they were read from.
+struct Cached {
+    stamps: Vec<Stamp>,
+    cookies: SiteCookie,
+    used: Instant,
+}
+
+struct Server {
+    specs: Vec<BrowserSpec>,
+    backend: Backend,
+    format: String,
+    token: String,
+    cache: Mutex<HashMap<String, Cached>>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn new(status: u16, content_type: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type,
+            body,
+        }
+    }
+
+    /// The JSON error object of `--format json`, with a status matching its exit code.
+    fn error(err: &(dyn std::error::Error + 'static)) -> Self {
+        let failure = Failure::of(err);
+        let status = match failure {
+            Failure::Usage | Failure::Unsupported => 400,
+            Failure::Empty | Failure::NotFound => 404,
+            Failure::Locked => 423,
+            Failure::Key | Failure::Other => 500,
+        };
+        let object = serde_json::json!({
+            "error": failure.kind(),
+            "code": failure.code(),
+            "message": err.to_string(),
+        });
+        Self::new(status, "application/json", object.to_string())
+    }
+
+    fn write(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            423 => "Locked",
+            _ => "Internal Server Error",
+        };
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        );
+        if self.status == 401 {
+            head.push_str("WWW-Authenticate: Bearer\r\n");
+        }
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.write_all(self.body.as_bytes())?;
+        stream.flush()
+    }
+}
+
+impl Server {
+    fn handle<S: Read + Write>(&self, mut stream: S) {
+        let response = match read_request(&mut stream) {
+            Some((method, target, auth)) => self.respond(&method, &target, auth.as_deref()),
+            None => Response::new(400, "text/plain", "bad request\n".into()),
+        };
+        let _ = response.write(&mut stream);
+    }
+
+    fn respond(&self, method: &str, target: &str, auth: Option<&str>) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if method != "GET" {
+            return Response::new(405, "text/plain", "only GET is supported\n".into());
+        }
+        if path == "/health" {
+            return Response::new(200, "text/plain", "ok\n".into());
+        }
+        let token = auth
+            .and_then(|auth| auth.strip_prefix("Bearer "))
+            .map(str::trim);
+        if !token.is_some_and(|token| same_token(token, &self.token)) {
+            return Response::new(401, "text/plain", "unauthorized\n".into());
+        }
+        if path != "/cookies" {
+            return Response::new(404, "text/plain", "not found\n".into());
+        }
+        let params: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        match self.cookies(&params, param("site"), param("browser"), param("format")) {
+            Ok(response) => response,
+            Err(err) => Response::error(err.as_ref()),
+        }
+    }
+
+    fn cookies(
+        &self,
+        params: &[(String, String)],
+        site: Option<&str>,
+        browser: Option<&str>,
+        format: Option<&str>,
+    ) -> MyResult<Response> {
+        let site = site.ok_or_else(|| gcookie::Error::InvalidSite("missing site".into()))?;
+        let format: Format = format.unwrap_or(&self.format).parse()?;
+        let specs = match browser {
+            Some(browser) => BrowserSpec::parse_list(browser)?,
+            None => self.specs.clone(),
+        };
+        let names: Vec<&String> = params
+            .iter()
+            .filter(|(k, _)| k == "name")
+            .map(|(_, v)| v)
+            .collect();
+
+        let mut cookies = self.read(&specs, site)?;
+        if !names.is_empty() {
+            cookies.retain(|cookie| names.contains(&&cookie.name));
+        }
+        if cookies.is_empty() {
+            return Err(Box::new(super::exit::NoCookies(site.to_string())));
+        }
+        let body = render_with(&cookies, format, &EnvOptions::default())?;
+        Ok(Response::new(200, format.content_type(), body))
+    }
+
+    /// Read the site, or answer from the cache while the files of every spec are unchanged.
+    fn read(&self, specs: &[BrowserSpec], site: &str) -> MyResult<SiteCookie> {
+        let key = format!(
+            "{}\n{}",
+            specs
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            site
+        );
+        // sources without a file to watch are read every time
+        let stamps: Option<Vec<Stamp>> = specs
+            .iter()
+            .map(watched_files)
+            .collect::<Option<Vec<_>>>()
+            .map(|files| files.iter().flatten().map(|path| stamp(path)).collect());
+        if let Some(stamps) = &stamps {
+            let mut cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+            if let Some(cached) = cache.get_mut(&key).filter(|c| &c.stamps == stamps) {
+                cached.used = Instant::now();
+                return Ok(cached.cookies.clone());
+            }
+        }
+        let (_, cookies) = self.backend.load_cookies_by_specs(site, specs)?;
+        if let Some(stamps) = stamps {
+            self.store(key, stamps, cookies.clone());
+        }
+        Ok(cookies)
+    }
+
+    fn store(&self, key: String, stamps: Vec<Stamp>, cookies: SiteCookie) {
+        let mut cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+        if cache.len() >= MAX_CACHED && !cache.contains_key(&key) {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        let used = Instant::now();
+        cache.insert(
+            key,
+            Cached {
+                stamps,
+                cookies,
+                used,
+            },
+        );
+    }
+}
+
+/// Read the request line and the `Authorization` header; the body is ignored.
+fn read_request(stream: &mut impl Read) -> Option<(String, String, Option<String>)> {
+    let mut reader = BufReader::new(stream.take(16 * 1024));
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut auth = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).ok()? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                auth = Some(value.trim().to_string());
+            }
+        }
+    }
+    Some((method, target, auth))
+}
+
+/// Compare tokens in time independent of where they differ.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The files whose changes invalidate the cookies read from `spec`; `None` for registered
+/// sources, which cannot be watched.
+fn watched_files(spec: &BrowserSpec) -> Option<Vec<PathBuf>> {
+    if source::registered(&spec.browser).is_some() {
+        return None;
+    }
+    if spec.browser.eq_ignore_ascii_case(vault::NAME) {
+        return Some(vec![PathBuf::from(spec.profile_text()?)]);
+    }
+    let database = match spec.resolve().ok()? {
+        (Family::Gecko, path) => FirefoxProfile::from_path(&path).cookies_path(),
+        (Family::Chromium, path) => ChromiumProfile::from_path(&path).cookies_path(),
+    };
+    // SQLite keeps recent writes in the WAL file until a checkpoint
+    let wal = PathBuf::from(format!("{}-wal", database.display()));
+    Some(vec![database, wal])
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    /// A server reading a Firefox profile in `dir` holding `sid=old` for bing.com.
+    fn server(dir: &Path) -> Server {
+        let conn = Connection::open(dir.join("cookies.sqlite")).unwrap();
+        conn.execute_batch("CREATE TABLE moz_cookies (id INTEGER PRIMARY KEY, originAttributes TEXT NOT NULL DEFAULT '', name TEXT, value TEXT, host TEXT, path TEXT, expiry INTEGER, lastAccessed INTEGER, creationTime INTEGER, isSecure INTEGER, isHttpOnly INTEGER, inBrowserElement INTEGER DEFAULT 0, sameSite INTEGER DEFAULT 0, rawSameSite INTEGER DEFAULT 0, schemeMap INTEGER DEFAULT 0, isPartitionedAttributeSet INTEGER DEFAULT 0, CONSTRAINT moz_uniqueid UNIQUE (name, host, path, originAttributes));
+            INSERT INTO moz_cookies (name, value, host, path, expiry, lastAccessed, creationTime, isSecure, isHttpOnly) VALUES ('sid', 'old', '.bing.com', '/', 4102444800, 0, 0, 1, 1);").unwrap();
+        Server {
+            specs: vec![format!("firefox:{}", dir.display()).parse().unwrap()],
+            backend: Backend::Native,
+            format: "header".into(),
+            token: "secret".into(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn read_request_ok() {
+        let mut request: &[u8] =
+            b"GET /cookies?site=bing.com HTTP/1.1\r\nHost: localhost\r\nauthorization: Bearer secret\r\n\r\nbody";
+        assert_eq!(
+            read_request(&mut request),
+            Some((
+                "GET".into(),
+                "/cookies?site=bing.com".into(),
+                Some("Bearer secret".into())
+            ))
+        );
+        let mut request: &[u8] = b"GET /health HTTP/1.1\r\n\r\n";
+        assert_eq!(
+            read_request(&mut request),
+            Some(("GET".into(), "/health".into(), None))
+        );
+        assert_eq!(read_request(&mut &b""[..]), None);
+        assert_eq!(read_request(&mut &b"GET\r\n\r\n"[..]), None);
+    }
+
+    #[test]
+    fn respond_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(dir.path());
+        let auth = Some("Bearer secret");
+        let status = |method, target, auth| server.respond(method, target, auth).status;
+        assert_eq!(status("GET", "/health", None), 200);
+        assert_eq!(status("GET", "/cookies?site=bing.com", None), 401);
+        assert_eq!(
+            status("GET", "/cookies?site=bing.com", Some("Bearer wrong")),
+            401
+        );
+        assert_eq!(status("GET", "/cookies?site=bing.com", Some("secret")), 401);
+        assert_eq!(status("GET", "/other", auth), 404);
+        assert_eq!(status("POST", "/cookies?site=bing.com", auth), 405);
+        assert_eq!(status("GET", "/cookies", auth), 400);
+        assert_eq!(
+            status("GET", "/cookies?site=bing.com&format=xml", auth),
+            400
+        );
+        assert_eq!(status("GET", "/cookies?site=example.com", auth), 404);
+
+        let response = server.respond("GET", "/cookies?site=bing.com", auth);
+        assert_eq!((response.status, response.body.as_str()), (200, "sid=old"));
+        let response = server.respond("GET", "/cookies?site=bing.com&format=har", auth);
+        assert_eq!(response.content_type, "application/json");
+        let response = server.respond("GET", "/cookies?site=bing.com&name=uid", auth);
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn cache_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(dir.path());
+        let database = dir.path().join("cookies.sqlite");
+        let read = || server.read(&server.specs, "bing.com").unwrap().to_string();
+        assert_eq!(read(), "sid=old");
+
+        // a change keeping the size and time of the file is not seen
+        let modified = fs::metadata(&database).unwrap().modified().unwrap();
+        let conn = Connection::open(&database).unwrap();
+        conn.execute("UPDATE moz_cookies SET value = 'new'", [])
+            .unwrap();
+        drop(conn);
+        let file = fs::File::options().write(true).open(&database).unwrap();
+        file.set_modified(modified).unwrap();
+        assert_eq!(read(), "sid=old");
+        file.set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(read(), "sid=new");
+
+        for n in 0..MAX_CACHED + 10 {
+            server
+                .read(&server.specs, &format!("{}.bing.com", n))
+                .unwrap();
+        }
+        let cache = server.cache.lock().unwrap();
+        assert_eq!(cache.len(), MAX_CACHED);
+        assert!(!cache.keys().any(|key| key.ends_with("\nbing.com")));
+    }
+}
@@ -0,0 +1,198 @@
+use clap::{arg, ArgAction, ArgMatches, Command};
+use gcookie::browser::{
+    cookie::Cookie,
+    import::{changes, import_cookies, replace_cookies},
+    load_cookies_by_queries,
+    spec::{BrowserSpec, ContainerRef},
+    utils::format_unix_time,
+};
+use gcookie::snapshot::{Snapshot, Snapshots};
+
+use super::{
+    allow_empty_arg, browser_args_without_backend, exit, import::print_imported, print_table,
+    target, MyResult,
+};
+
+pub fn command() -> Command {
+    Command::new("snapshot")
+        .about("save the cookies of a profile and restore them later")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("save")
+                .about("save the cookies of a site, or of the whole profile")
+                // snapshots are read natively
+                .args(browser_args_without_backend())
+                .arg(
+                    arg!(--name [name] "save only the cookies with this name; repeat for more")
+                        .action(ArgAction::Append),
+                )
+                .arg(arg!(--force "replace a snapshot of the same name"))
+                .arg(allow_empty_arg())
+                .arg(arg!(<snapshot> "name of the snapshot"))
+                .arg(arg!([site] "URL or host of the site or @preset; every cookie of the profile if omitted")),
+        )
+        .subcommand(
+            Command::new("list").about("list the saved snapshots").arg(
+                arg!(--format [format] "output format")
+                    .value_parser(["table", "json"])
+                    .default_value("table"),
+            ),
+        )
+        .subcommand(
+            Command::new("restore")
+                .about("write the cookies of a snapshot back into a profile")
+                .arg(arg!(--to [to] "browser spec to write, the profile the snapshot was taken from if omitted"))
+                .arg(arg!(--clean "first delete the cookies of the snapshot's sites, leaving only those of the snapshot"))
+                .arg(arg!(-n --"dry-run" "show what would change without writing"))
+                .arg(arg!(<snapshot> "name of the snapshot")),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    let snapshots = Snapshots::find()?;
+    match matches.subcommand() {
+        Some(("save", sub)) => save(sub, &snapshots),
+        Some(("list", sub)) => list(sub, &snapshots),
+        Some(("restore", sub)) => restore(sub, &snapshots),
+        _ => unreachable!("a subcommand is required"),
+    }
+}
+
+fn save(matches: &ArgMatches, snapshots: &Snapshots) -> MyResult<()> {
+    let mut target = target(matches)?;
+    target.names.extend(
+        matches
+            .get_many::<String>("name")
+            .into_iter()
+            .flatten()
+            .cloned(),
+    );
+    let name = matches.get_one::<String>("snapshot").unwrap();
+    let snapshot = Snapshot::take(
+        name,
+        &target.specs[0],
+        target.sites.clone(),
+        target.names.clone(),
+    )?;
+    for warning in snapshot.cookies.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    if snapshot.cookies.is_empty() && !matches.get_flag("allow-empty") {
+        let sites = if target.sites.is_empty() {
+            target.specs[0].to_string()
+        } else {
+            target.sites.join(", ")
+        };
+        return Err(Box::new(exit::NoCookies(sites)));
+    }
+    let path = snapshots.save(&snapshot, matches.get_flag("force"))?;
+    println!(
+        "{}: {} cookies of {} hosts",
+        path.display(),
+        snapshot.cookies.len(),
+        snapshot.hosts()
+    );
+    Ok(())
+}
+
+fn list(matches: &ArgMatches, snapshots: &Snapshots) -> MyResult<()> {
+    let (listed, warnings) = snapshots.list()?;
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    let sites = |snapshot: &Snapshot| {
+        if snapshot.sites.is_empty() {
+            "all".to_string()
+        } else {
+            snapshot.sites.join(",")
+        }
+    };
+    if matches.get_one::<String>("format").unwrap() == "json" {
+        let json: Vec<_> = listed
+            .iter()
+            .map(|snapshot| {
+                serde_json::json!({
+                    "name": snapshot.name,
+                    "created": snapshot.created,
+                    "source": snapshot.source,
+                    "sites": snapshot.sites,
+                    "names": snapshot.names,
+                    "cookies": snapshot.cookies.len(),
+                    "hosts": snapshot.hosts(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = listed
+        .iter()
+        .map(|snapshot| {
+            vec![
+                snapshot.name.clone(),
+                format_unix_time(snapshot.created),
+                sites(snapshot),
+                snapshot.cookies.len().to_string(),
+                snapshot.hosts().to_string(),
+                snapshot.source.clone(),
+            ]
+        })
+        .collect();
+    print_table(
+        &["NAME", "CREATED", "SITES", "COOKIES", "HOSTS", "SOURCE"],
+        &rows,
+    );
+    Ok(())
+}
+
+fn restore(matches: &ArgMatches, snapshots: &Snapshots) -> MyResult<()> {
+    let snapshot = snapshots.load(matches.get_one::<String>("snapshot").unwrap())?;
+    let to: BrowserSpec = match matches.get_one::<String>("to") {
+        Some(to) => to.parse()?,
+        None => snapshot.source.parse()?,
+    };
+    let (_, path) = to.resolve()?;
+    let queries = snapshot.queries()?;
+    // cookies are written outside of every container unless `to` names one
+    let mut written = to.clone();
+    if written.is_firefox() && written.container.is_none() {
+        written.container = Some(ContainerRef::None);
+    }
+    if matches.get_flag("dry-run") {
+        let existing = load_cookies_by_queries(&written, &queries)?;
+        let row = |change: &str, cookie: &Cookie| {
+            vec![
+                change.to_string(),
+                cookie.host.clone(),
+                cookie.path.clone(),
+                cookie.name.clone(),
+            ]
+        };
+        let mut rows: Vec<Vec<String>> = changes(&existing, &snapshot.cookies)
+            .into_iter()
+            .zip(snapshot.cookies.iter())
+            .map(|(change, cookie)| row(change.name(), cookie))
+            .collect();
+        if matches.get_flag("clean") {
+            // the cookies of the snapshot's sites that it does not hold
+            let stale = existing.iter().filter(|old| {
+                !snapshot.cookies.iter().any(|cookie| {
+                    old.host == cookie.host && old.name == cookie.name && old.path == cookie.path
+                })
+            });
+            rows.extend(stale.map(|cookie| row("delete", cookie)));
+        }
+        print_table(&["change", "host", "path", "name"], &rows);
+        return Ok(());
+    }
+    // deleting and writing in one transaction, a failed restore leaves the profile as it was
+    let imported = if matches.get_flag("clean") {
+        let imported = replace_cookies(&written, &queries, &snapshot.cookies)?;
+        println!("{}: {} deleted", path.display(), imported.deleted.len());
+        imported
+    } else {
+        import_cookies(&to, &snapshot.cookies)?
+    };
+    print_imported(&path, &imported);
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use clap::{arg, ArgMatches, Command};
+use gcookie::vault::{generate_key_file, write_vault, KeyKind, VaultKey};
+use std::{env, path::PathBuf};
+
+use super::{allow_empty_arg, input_args, load_input_cookies, MyResult};
+
+pub fn command() -> Command {
+    Command::new("vault")
+        .about("write cookies into an encrypted file, read back with -c vault:FILE")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("create")
+                .about("encrypt cookies into a vault file")
+                .arg(
+                    arg!(-o --output <output> "vault file to write")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--"key-file" [key_file] "key file to encrypt with, see `vault keygen`")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--"passphrase-file" [passphrase_file] "file whose first line is the passphrase to encrypt with")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .conflicts_with("key-file"),
+                )
+                .args(input_args())
+                .arg(
+                    arg!(--source [source] "read the cookies of the site from this browser spec instead of a file")
+                        .conflicts_with_all(["input", "from"])
+                        .requires("site"),
+                )
+                .arg(allow_empty_arg())
+                .arg(arg!([site] "keep only the cookies of this site or @preset; also the host of cookies without a domain")),
+        )
+        .subcommand(
+            Command::new("keygen")
+                .about("write a new random key file")
+                .arg(arg!(<file> "key file to create").value_parser(clap::value_parser!(PathBuf))),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> MyResult<()> {
+    match matches.subcommand() {
+        Some(("create", sub)) => create(sub),
+        Some(("keygen", sub)) => {
+            let path = sub.get_one::<PathBuf>("file").unwrap();
+            generate_key_file(path)?;
+            println!("{}", path.display());
+            Ok(())
+        }
+        _ => unreachable!("a subcommand is required"),
+    }
+}
+
+fn create(matches: &ArgMatches) -> MyResult<()> {
+    let key = vault_key(matches)?;
+    let cookies = load_input_cookies(matches)?;
+    let path = matches.get_one::<PathBuf>("output").unwrap();
+    write_vault(path, &cookies, &key)?;
+    println!(
+        "{}: {} cookies, sealed with a {}",
+        path.display(),
+        cookies.len(),
+        key.kind().name()
+    );
+    Ok(())
+}
+
+/// The key of `--key-file` or `--passphrase-file`, else the passphrase or key file
+/// given by the `GCOOKIE_VAULT_*` variables or the default key file.
+fn vault_key(matches: &ArgMatches) -> MyResult<VaultKey> {
+    if let Some(path) = matches.get_one::<PathBuf>("key-file") {
+        return Ok(VaultKey::key_file(path)?);
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("passphrase-file") {
+        return Ok(VaultKey::passphrase_file(path)?);
+    }
+    if env::var_os("GCOOKIE_VAULT_PASSPHRASE").is_some_and(|p| !p.is_empty()) {
+        return Ok(VaultKey::from_env(KeyKind::Passphrase)?);
+    }
+    VaultKey::from_env(KeyKind::KeyFile).map_err(|_| {
+        "no key: give --key-file or --passphrase-file, or set GCOOKIE_VAULT_PASSPHRASE or GCOOKIE_VAULT_KEY_FILE"
+            .into()
+    })
+}
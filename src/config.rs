@@ -0,0 +1,214 @@
+//! Defaults and presets read from `config.toml`.
+//!
+//! ```toml
+//! browser = "edge"
+//! profile = "Work"
+//! backend = "native"
+//!
+//! [presets.github]
+//! browser = "firefox"
+//! profile = "work"
+//! sites = ["github.com", "api.github.com"]
+//! names = ["user_session", "logged_in"]
+//! ```
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
+
+/// Which profile to read and how to print it. Every field is optional so that flags,
+/// presets, environment variables and the config file can be layered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    pub browser: Option<String>,
+    pub profile: Option<String>,
+    /// Profile directory; Firefox browsers read it as a Firefox profile, others as Chromium.
+    pub path: Option<PathBuf>,
+    pub format: Option<String>,
+    /// `native`, `rookie` or `auto`, see [`crate::browser::Backend`].
+    pub backend: Option<String>,
+}
+
+impl Selection {
+    /// `GCOOKIE_BROWSER`, `GCOOKIE_PROFILE`, `GCOOKIE_PATH`, `GCOOKIE_FORMAT` and
+    /// `GCOOKIE_BACKEND`.
+    pub fn from_env() -> Self {
+        let var = |name| env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Self {
+            browser: var("GCOOKIE_BROWSER"),
+            profile: var("GCOOKIE_PROFILE"),
+            path: var("GCOOKIE_PATH").map(PathBuf::from),
+            format: var("GCOOKIE_FORMAT"),
+            backend: var("GCOOKIE_BACKEND"),
+        }
+    }
+
+    /// Fill what `self` leaves open from `lower`.
+    ///
+    /// A browser, profile or path set here also drops the profile and path of `lower`,
+    /// which may belong to another browser.
+    pub fn or(self, lower: Selection) -> Selection {
+        let located = self.browser.is_some() || self.profile.is_some() || self.path.is_some();
+        Selection {
+            profile: if located { self.profile } else { lower.profile },
+            path: if located { self.path } else { lower.path },
+            browser: self.browser.or(lower.browser),
+            format: self.format.or(lower.format),
+            backend: self.backend.or(lower.backend),
+        }
+    }
+}
+
+/// A named set of options, used on the command line as `@name`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    pub browser: Option<String>,
+    pub profile: Option<String>,
+    pub path: Option<PathBuf>,
+    pub format: Option<String>,
+    pub backend: Option<String>,
+    /// Sites whose cookies are read together.
+    pub sites: Vec<String>,
+    /// Keep only the cookies with these names; all of them when empty.
+    pub names: Vec<String>,
+}
+
+impl Preset {
+    pub fn selection(&self) -> Selection {
+        Selection {
+            browser: self.browser.clone(),
+            profile: self.profile.clone(),
+            path: self.path.clone(),
+            format: self.format.clone(),
+            backend: self.backend.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub browser: Option<String>,
+    pub profile: Option<String>,
+    pub path: Option<PathBuf>,
+    pub format: Option<String>,
+    pub backend: Option<String>,
+    pub presets: BTreeMap<String, Preset>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/gcookie/config.toml`, or `gcookie/config.toml` in the platform
+    /// config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(dirs::config_dir)?;
+        Some(dir.join("gcookie").join("config.toml"))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|err| Error::Config(err.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).map_err(|_| Error::NotFound(path.display().to_string()))?;
+        toml::from_str(&text).map_err(|err| Error::Config(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Load `path`, else the file named by `GCOOKIE_CONFIG`, else [`Config::default_path`].
+    ///
+    /// A missing default file is an empty config; a file that was asked for must exist.
+    pub fn find(path: Option<&Path>) -> Result<Self> {
+        if let Some(path) = path {
+            return Self::load(path);
+        }
+        if let Some(path) = env::var_os("GCOOKIE_CONFIG").filter(|p| !p.is_empty()) {
+            return Self::load(Path::new(&path));
+        }
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn selection(&self) -> Selection {
+        Selection {
+            browser: self.browser.clone(),
+            profile: self.profile.clone(),
+            path: self.path.clone(),
+            format: self.format.clone(),
+            backend: self.backend.clone(),
+        }
+    }
+
+    pub fn preset(&self, name: &str) -> Result<&Preset> {
+        self.presets
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("no preset @{}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config_ok() {
+        let config = Config::parse(
+            r#"
+            browser = "edge"
+            profile = "Work"
+
+            [presets.github]
+            browser = "firefox"
+            sites = ["github.com", "api.github.com"]
+            names = ["user_session"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.browser.as_deref(), Some("edge"));
+        let github = config.preset("github").unwrap();
+        assert_eq!(github.sites, ["github.com", "api.github.com"]);
+        assert_eq!(github.names, ["user_session"]);
+        assert!(matches!(config.preset("gitlab"), Err(Error::Config(_))));
+        assert!(matches!(
+            Config::parse("brwoser = \"edge\""),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn selection_or_ok() {
+        let config = Selection {
+            browser: Some("firefox".into()),
+            profile: Some("default".into()),
+            format: Some("json".into()),
+            ..Default::default()
+        };
+        // a profile given on the command line keeps the configured browser
+        let flags = Selection {
+            profile: Some("work".into()),
+            ..Default::default()
+        };
+        let merged = flags.or(config.clone());
+        assert_eq!(merged.browser.as_deref(), Some("firefox"));
+        assert_eq!(merged.profile.as_deref(), Some("work"));
+        assert_eq!(merged.format.as_deref(), Some("json"));
+        // another browser drops the configured profile
+        let flags = Selection {
+            browser: Some("edge".into()),
+            ..Default::default()
+        };
+        let merged = flags.or(config.clone());
+        assert_eq!(merged.browser.as_deref(), Some("edge"));
+        assert_eq!(merged.profile, None);
+        assert_eq!(Selection::default().or(config.clone()), config);
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fmt, io};
+
+/// Errors returned by gcookie.
+#[derive(Debug)]
+pub enum Error {
+    /// A browser, profile or cookie database does not exist.
+    NotFound(String),
+    /// A running browser holds the cookie database.
+    Locked(String),
+    UnsupportedBrowser(String),
+    /// The key protecting cookie values cannot be retrieved.
+    KeyUnavailable(String),
+    /// A cookie value cannot be decrypted.
+    DecryptFailed(String),
+    /// The cookie database or `Local State` has an unexpected layout.
+    Schema(String),
+    /// The site is neither a host nor a URL with a host.
+    InvalidSite(String),
+    /// Error reported by the rookie backend.
+    Backend(String),
+    /// The config file is not valid.
+    Config(String),
+    /// A `BROWSER[+KEYRING][:PROFILE][::CONTAINER]` specification is not valid.
+    InvalidSpec(String),
+    /// An output template does not parse.
+    Template(String),
+    /// Cookies given as input, like a `cookies.txt` or HAR file, do not parse.
+    Parse(String),
+    /// A cookie value cannot be written in the output format.
+    Unrepresentable(String),
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(msg) => write!(f, "{} not exists", msg),
+            Error::Locked(msg) => write!(f, "{}", msg),
+            Error::UnsupportedBrowser(browser) => write!(f, "Unsupported browser: {}", browser),
+            Error::KeyUnavailable(msg) => write!(f, "cannot get key: {}", msg),
+            Error::DecryptFailed(msg) => write!(f, "cannot decrypt {}", msg),
+            Error::Schema(msg) => write!(f, "unexpected data: {}", msg),
+            Error::InvalidSite(site) => write!(f, "invalid site: {}", site),
+            Error::Backend(msg) => write!(f, "{}", msg),
+            Error::Config(msg) => write!(f, "invalid config: {}", msg),
+            Error::InvalidSpec(msg) => write!(f, "invalid browser spec: {}", msg),
+            Error::Template(msg) => write!(f, "invalid template: {}", msg),
+            Error::Parse(msg) => write!(f, "cannot parse cookies: {}", msg),
+            Error::Unrepresentable(msg) => write!(f, "cannot write {}", msg),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Sqlite(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Sqlite(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Sqlite(err)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(err: url::ParseError) -> Self {
+        Error::InvalidSite(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Schema(err.to_string())
+    }
+}
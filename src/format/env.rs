@@ -0,0 +1,344 @@
+//! Shell `export` lines, `.env` files and Kubernetes Secrets.
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::browser::cookie::SiteCookie;
+use crate::{Error, Result};
+
+/// Naming of the variables, or of the Secret and its data keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvOptions {
+    /// Name of the combined variable and prefix of the per-cookie ones (`COOKIE_SID`);
+    /// the Secret is named after it too.
+    pub name: String,
+    /// One variable or data key holding the `Cookie` header instead of one per cookie.
+    pub combined: bool,
+}
+
+impl Default for EnvOptions {
+    fn default() -> Self {
+        Self {
+            name: "COOKIE".to_string(),
+            combined: false,
+        }
+    }
+}
+
+/// Characters that need no quoting in a shell word or a `.env` value.
+fn is_plain(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"_-.,:/@%+=".contains(&b))
+}
+
+/// Quote a value for POSIX shells: single quotes, with `'` written as `'\''`.
+pub fn shell_quote(value: &str) -> String {
+    if is_plain(value) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// Quote a value for `.env` files as read by Docker Compose, python-dotenv and dotenv.
+///
+/// Their escapes differ, so no escape is written: single quotes keep everything literal,
+/// double quotes are used when the value holds one and nothing they would expand.
+/// Values fitting neither, like `it's "$HOME"`, are refused.
+pub fn dotenv_quote(value: &str) -> Result<String> {
+    if is_plain(value) {
+        Ok(value.to_string())
+    } else if value.contains(['\n', '\r'])
+        // python-dotenv reads `\\` and `\'` as escapes even in single quotes
+        || value.contains(r"\\")
+        || value.ends_with('\\')
+    {
+        Err(unrepresentable(value))
+    } else if !value.contains('\'') {
+        Ok(format!("'{}'", value))
+    } else if !value.contains(['"', '\\', '$', '`']) {
+        Ok(format!("\"{}\"", value))
+    } else {
+        Err(unrepresentable(value))
+    }
+}
+
+fn unrepresentable(value: &str) -> Error {
+    Error::Unrepresentable(format!(
+        "{:?} in a .env file; use --format env or json",
+        value
+    ))
+}
+
+/// Refuse a variable name that shells and `.env` readers would not take: letters, digits
+/// and `_`, not starting with a digit.
+fn check_var_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "env name {:?}: use letters, digits and _, not starting with a digit",
+            name
+        )))
+    }
+}
+
+/// Turn a cookie name into a variable name: `PREFIX_NAME`, upper case, `_` for anything
+/// else than letters and digits.
+fn var_name(prefix: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_{}", prefix, name)
+}
+
+/// `(name, value)` pairs, with `_2`, `_3`... appended to repeated names, such as the
+/// same cookie set on two paths.
+fn variables(
+    cookies: &SiteCookie,
+    options: &EnvOptions,
+    name: impl Fn(&str) -> String,
+) -> Vec<(String, String)> {
+    if options.combined {
+        return vec![(options.name.clone(), cookies.to_string())];
+    }
+    let mut vars: Vec<(String, String)> = vec![];
+    for cookie in cookies.iter() {
+        let base = name(&cookie.name);
+        let mut var = base.clone();
+        let mut n = 1;
+        while vars.iter().any(|(v, _)| *v == var) {
+            n += 1;
+            var = format!("{}_{}", base, n);
+        }
+        vars.push((var, cookie.value.clone()));
+    }
+    vars
+}
+
+/// `export NAME=value` lines for `eval "$(gcookie ...)"`.
+pub fn render_env(cookies: &SiteCookie, options: &EnvOptions) -> Result<String> {
+    check_var_name(&options.name)?;
+    Ok(
+        variables(cookies, options, |name| var_name(&options.name, name))
+            .into_iter()
+            .map(|(var, value)| format!("export {}={}\n", var, shell_quote(&value)))
+            .collect(),
+    )
+}
+
+/// `NAME=value` lines of a `.env` file.
+pub fn render_dotenv(cookies: &SiteCookie, options: &EnvOptions) -> Result<String> {
+    check_var_name(&options.name)?;
+    variables(cookies, options, |name| var_name(&options.name, name))
+        .into_iter()
+        .map(|(var, value)| Ok(format!("{}={}\n", var, dotenv_quote(&value)?)))
+        .collect()
+}
+
+/// A Kubernetes `Secret` manifest with a base64 data key per cookie, or a single `cookie`
+/// key holding the header when combined.
+pub fn render_k8s_secret(cookies: &SiteCookie, options: &EnvOptions) -> Result<String> {
+    // names are DNS subdomains: lower case letters, digits, '-' and '.', alphanumeric at
+    // both ends; `COOKIE_NAME` is taken as `cookie-name`
+    let secret = options.name.to_lowercase().replace('_', "-");
+    let valid = secret.len() <= 253
+        && secret
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-.".contains(c))
+        && secret.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && secret.ends_with(|c: char| c.is_ascii_alphanumeric());
+    if !valid {
+        return Err(Error::Config(format!(
+            "env name {:?} is no Secret name: use letters, digits, -, _ and ., starting and ending with a letter or digit",
+            options.name
+        )));
+    }
+    let combined = EnvOptions {
+        name: "cookie".to_string(),
+        combined: options.combined,
+    };
+    // data keys may hold letters, digits, '-', '_' and '.'
+    let key = |name: &str| -> String {
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    let mut out = format!(
+        "apiVersion: v1\nkind: Secret\nmetadata:\n  name: {}\ntype: Opaque\ndata:\n",
+        secret
+    );
+    for (key, value) in variables(cookies, &combined, key) {
+        out.push_str(&format!(
+            "  {}: {}\n",
+            key,
+            general_purpose::STANDARD.encode(value)
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::cookie::Cookie;
+
+    fn jar() -> SiteCookie {
+        let cookie = |name: &str, value: &str| Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        };
+        vec![
+            cookie("sid", "a1"),
+            cookie("user-pref", "it's $HOME"),
+            cookie("sid", "b2"),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn quote_ok() {
+        assert_eq!(shell_quote("a1"), "a1");
+        assert_eq!(shell_quote("it's $HOME"), r"'it'\''s $HOME'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(dotenv_quote("a b").unwrap(), "'a b'");
+        assert_eq!(dotenv_quote("it's 1").unwrap(), r#""it's 1""#);
+        assert!(matches!(
+            dotenv_quote("it's $HOME"),
+            Err(Error::Unrepresentable(_))
+        ));
+        assert!(dotenv_quote("a\nb").is_err());
+        assert!(dotenv_quote(r"a\\b").is_err());
+        assert!(dotenv_quote(r"C:\").is_err());
+    }
+
+    /// Undo the backslash escapes of `escapes`, as python-dotenv does in quoted values.
+    fn unescape(text: &str, escapes: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match chars.peek() {
+                Some(&next) if c == '\\' && escapes.contains(next) => {
+                    out.push(if next == 'n' { '\n' } else { next });
+                    chars.next();
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Read a `.env` value back the way python-dotenv and dotenv (Node) do.
+    fn read_dotenv(value: &str) -> (String, String) {
+        if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+            return (unescape(inner, r"\'"), inner.to_string());
+        }
+        if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            return (unescape(inner, r#"\'"n"#), inner.replace(r"\n", "\n"));
+        }
+        (value.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn dotenv_read_back_ok() {
+        for value in [
+            "a1",
+            "x=1&y=2",
+            "",
+            "a b",
+            "it's 1",
+            r#"{"a":"b $c \n"}"#,
+            r"C:\Users",
+        ] {
+            let quoted = dotenv_quote(value).unwrap();
+            let (python, node) = read_dotenv(&quoted);
+            assert_eq!(python, value, "python-dotenv reading {}", quoted);
+            assert_eq!(node, value, "dotenv reading {}", quoted);
+        }
+    }
+
+    #[test]
+    fn render_env_ok() {
+        let options = EnvOptions::default();
+        assert_eq!(
+            render_env(&jar(), &options).unwrap(),
+            "export COOKIE_SID=a1\nexport COOKIE_USER_PREF='it'\\''s $HOME'\nexport COOKIE_SID_2=b2\n"
+        );
+        let combined = EnvOptions {
+            name: "BING".to_string(),
+            combined: true,
+        };
+        assert!(render_dotenv(&jar(), &combined).is_err());
+        let mut jar = jar();
+        jar.retain(|cookie| cookie.name == "sid");
+        assert_eq!(
+            render_dotenv(&jar, &combined).unwrap(),
+            "BING='sid=a1; sid=b2'\n"
+        );
+        for name in ["", "1ST", "MY-COOKIE", "A B", "X;rm"] {
+            let options = EnvOptions {
+                name: name.to_string(),
+                combined: false,
+            };
+            assert!(
+                matches!(render_env(&jar, &options), Err(Error::Config(_))),
+                "{:?}",
+                name
+            );
+            assert!(render_dotenv(&jar, &options).is_err());
+        }
+    }
+
+    #[test]
+    fn render_k8s_secret_ok() {
+        let options = EnvOptions {
+            name: "BING_COOKIE".to_string(),
+            combined: false,
+        };
+        let secret = render_k8s_secret(&jar(), &options).unwrap();
+        assert!(secret.contains("  name: bing-cookie\n"));
+        assert!(secret.contains("  sid: YTE=\n"));
+        assert!(secret.contains("  user-pref: "));
+        assert!(secret.contains("  sid_2: YjI=\n"));
+        let combined = render_k8s_secret(
+            &jar(),
+            &EnvOptions {
+                combined: true,
+                ..options
+            },
+        )
+        .unwrap();
+        assert!(combined
+            .ends_with("data:\n  cookie: c2lkPWExOyB1c2VyLXByZWY9aXQncyAkSE9NRTsgc2lkPWIy\n"));
+        for name in ["", "_COOKIE", "my app", "a..b!"] {
+            let options = EnvOptions {
+                name: name.to_string(),
+                combined: false,
+            };
+            assert!(
+                matches!(render_k8s_secret(&jar(), &options), Err(Error::Config(_))),
+                "{:?}",
+                name
+            );
+        }
+    }
+}
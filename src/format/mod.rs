@@ -0,0 +1,481 @@
+//! Render cookies in the formats other tools read.
+use std::{fmt, str::FromStr};
+
+use crate::browser::cookie::{Cookie, SameSite, SiteCookie};
+use crate::browser::utils::format_iso_time;
+
+pub mod env;
+pub mod parse;
+pub mod template;
+
+pub use env::EnvOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `name=value; name2=value2`, the value of a `Cookie` request header.
+    Header,
+    /// An array of cookie objects with every attribute.
+    Json,
+    /// Netscape `cookies.txt`, read by curl, wget and yt-dlp.
+    Netscape,
+    /// Playwright `storageState`, passed to `browser.newContext({ storageState })`.
+    Playwright,
+    /// An array of Puppeteer cookie parameters for `page.setCookie(...cookies)`.
+    Puppeteer,
+    /// An array of Selenium cookie dicts for `driver.add_cookie(cookie)`.
+    Selenium,
+    /// An HTTPie session file, used with `http --session=./session.json`.
+    Httpie,
+    /// An array of Postman cookies, as in its cookie manager and `pm.cookies`.
+    Postman,
+    /// An Insomnia export holding a cookie jar, imported with Application > Import.
+    Insomnia,
+    /// The `cookies` array of a HAR 1.2 request or response.
+    Har,
+    /// `export COOKIE_NAME=value` lines, for `eval "$(gcookie get --format env ...)"`.
+    Env,
+    /// `COOKIE_NAME=value` lines of a `.env` file.
+    Dotenv,
+    /// A Kubernetes `Secret` manifest, applied with `kubectl apply -f`.
+    K8sSecret,
+}
+
+impl Format {
+    /// Every format name accepted by [`Format::from_str`].
+    pub const NAMES: &'static [&'static str] = &[
+        "header",
+        "json",
+        "netscape",
+        "playwright",
+        "puppeteer",
+        "selenium",
+        "httpie",
+        "postman",
+        "insomnia",
+        "har",
+        "env",
+        "dotenv",
+        "k8s-secret",
+    ];
+
+    /// Media type of the rendered text.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json
+            | Format::Playwright
+            | Format::Puppeteer
+            | Format::Selenium
+            | Format::Httpie
+            | Format::Postman
+            | Format::Insomnia
+            | Format::Har => "application/json",
+            Format::K8sSecret => "application/yaml",
+            Format::Header | Format::Netscape | Format::Env | Format::Dotenv => {
+                "text/plain; charset=utf-8"
+            }
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "header" => Ok(Format::Header),
+            "json" => Ok(Format::Json),
+            "netscape" | "cookies.txt" => Ok(Format::Netscape),
+            "playwright" => Ok(Format::Playwright),
+            "puppeteer" => Ok(Format::Puppeteer),
+            "selenium" => Ok(Format::Selenium),
+            "httpie" => Ok(Format::Httpie),
+            "postman" => Ok(Format::Postman),
+            "insomnia" => Ok(Format::Insomnia),
+            "har" => Ok(Format::Har),
+            "env" => Ok(Format::Env),
+            "dotenv" | ".env" => Ok(Format::Dotenv),
+            "k8s-secret" | "kubernetes" => Ok(Format::K8sSecret),
+            _ => Err(crate::Error::Config(format!(
+                "unsupported format {}; please use {}",
+                s,
+                Format::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Header => "header",
+            Format::Json => "json",
+            Format::Netscape => "netscape",
+            Format::Playwright => "playwright",
+            Format::Puppeteer => "puppeteer",
+            Format::Selenium => "selenium",
+            Format::Httpie => "httpie",
+            Format::Postman => "postman",
+            Format::Insomnia => "insomnia",
+            Format::Har => "har",
+            Format::Env => "env",
+            Format::Dotenv => "dotenv",
+            Format::K8sSecret => "k8s-secret",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Render cookies in the given format.
+pub fn render(cookies: &SiteCookie, format: Format) -> crate::Result<String> {
+    render_with(cookies, format, &EnvOptions::default())
+}
+
+/// Render cookies in the given format, naming variables and Secrets after `env`.
+pub fn render_with(
+    cookies: &SiteCookie,
+    format: Format,
+    env: &EnvOptions,
+) -> crate::Result<String> {
+    Ok(match format {
+        Format::Header => cookies.to_string(),
+        Format::Json => {
+            let json: Vec<_> = cookies.iter().map(cookie_json).collect();
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        Format::Netscape => {
+            let mut out = String::from("# Netscape HTTP Cookie File\n");
+            for cookie in cookies.iter() {
+                out.push_str(&netscape_line(cookie));
+                out.push('\n');
+            }
+            out
+        }
+        Format::Playwright => {
+            let cookies: Vec<_> = cookies.iter().map(playwright_json).collect();
+            let state = serde_json::json!({ "cookies": cookies, "origins": [] });
+            serde_json::to_string_pretty(&state).unwrap_or_default()
+        }
+        Format::Puppeteer => {
+            let json: Vec<_> = cookies.iter().map(puppeteer_json).collect();
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        Format::Selenium => {
+            let json: Vec<_> = cookies.iter().map(selenium_json).collect();
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        Format::Httpie => {
+            let cookies: Vec<_> = cookies.iter().map(httpie_json).collect();
+            let session = serde_json::json!({
+                "__meta__": {
+                    "about": "HTTPie session file",
+                    "help": "https://httpie.io/docs#sessions",
+                    "httpie": "3.2.2",
+                },
+                "auth": { "password": null, "type": null, "username": null },
+                "cookies": cookies,
+                "headers": [],
+            });
+            serde_json::to_string_pretty(&session).unwrap_or_default()
+        }
+        Format::Postman => {
+            let json: Vec<_> = cookies.iter().map(postman_json).collect();
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        Format::Insomnia => {
+            let cookies: Vec<_> = cookies.iter().enumerate().map(insomnia_json).collect();
+            let export = serde_json::json!({
+                "_type": "export",
+                "__export_format": 4,
+                "__export_source": "gcookie",
+                "resources": [{
+                    "_id": "jar_gcookie",
+                    "_type": "cookie_jar",
+                    "parentId": "__WORKSPACE_ID__",
+                    "name": "Default Jar",
+                    "cookies": cookies,
+                }],
+            });
+            serde_json::to_string_pretty(&export).unwrap_or_default()
+        }
+        Format::Har => {
+            let json: Vec<_> = cookies.iter().map(har_json).collect();
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        Format::Env => env::render_env(cookies, env)?,
+        Format::Dotenv => env::render_dotenv(cookies, env)?,
+        Format::K8sSecret => env::render_k8s_secret(cookies, env)?,
+    })
+}
+
+fn cookie_json(cookie: &Cookie) -> serde_json::Value {
+    serde_json::json!({
+        "domain": cookie.host,
+        "path": cookie.path,
+        "name": cookie.name,
+        "value": cookie.value,
+        "expires": cookie.expires,
+        "secure": cookie.secure,
+        "httpOnly": cookie.http_only,
+        "sameSite": cookie.same_site.to_string(),
+    })
+}
+
+/// `Strict`, `Lax` or `None`, the values automation tools accept; `None` when unset.
+fn same_site_name(same_site: SameSite) -> Option<&'static str> {
+    match same_site {
+        SameSite::Unspecified => None,
+        SameSite::None => Some("None"),
+        SameSite::Lax => Some("Lax"),
+        SameSite::Strict => Some("Strict"),
+    }
+}
+
+/// Playwright requires every field; session cookies expire at `-1` and an unset
+/// `sameSite` is written as `Lax`, which is how browsers treat it.
+fn playwright_json(cookie: &Cookie) -> serde_json::Value {
+    serde_json::json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.host,
+        "path": cookie.path,
+        "expires": cookie.expires.unwrap_or(-1),
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+        "sameSite": same_site_name(cookie.same_site).unwrap_or("Lax"),
+    })
+}
+
+fn puppeteer_json(cookie: &Cookie) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.host,
+        "path": cookie.path,
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+    });
+    if let Some(expires) = cookie.expires {
+        json["expires"] = expires.into();
+    }
+    if let Some(same_site) = same_site_name(cookie.same_site) {
+        json["sameSite"] = same_site.into();
+    }
+    json
+}
+
+fn selenium_json(cookie: &Cookie) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.host,
+        "path": cookie.path,
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+    });
+    if let Some(expires) = cookie.expires {
+        json["expiry"] = expires.into();
+    }
+    if let Some(same_site) = same_site_name(cookie.same_site) {
+        json["sameSite"] = same_site.into();
+    }
+    json
+}
+
+fn httpie_json(cookie: &Cookie) -> serde_json::Value {
+    serde_json::json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.host,
+        "path": cookie.path,
+        "secure": cookie.secure,
+        "expires": cookie.expires,
+    })
+}
+
+/// Postman and Insomnia keep cookies like the `tough-cookie` library: the domain without
+/// its leading dot, and `hostOnly` for cookies that do not apply to subdomains.
+fn postman_json(cookie: &Cookie) -> serde_json::Value {
+    serde_json::json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.host.trim_start_matches('.'),
+        "path": cookie.path,
+        "expires": cookie.expires.map(format_iso_time),
+        "hostOnly": !cookie.host.starts_with('.'),
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+        "session": cookie.expires.is_none(),
+    })
+}
+
+fn insomnia_json((index, cookie): (usize, &Cookie)) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "id": format!("gcookie-{}", index),
+        "key": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.host.trim_start_matches('.'),
+        "path": cookie.path,
+        "hostOnly": !cookie.host.starts_with('.'),
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+    });
+    if let Some(expires) = cookie.expires {
+        json["expires"] = format_iso_time(expires).into();
+    }
+    if let Some(creation) = cookie.creation {
+        json["creation"] = format_iso_time(creation).into();
+    }
+    if let Some(last_access) = cookie.last_access {
+        json["lastAccessed"] = format_iso_time(last_access).into();
+    }
+    json
+}
+
+fn har_json(cookie: &Cookie) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "path": cookie.path,
+        "domain": cookie.host,
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+    });
+    if let Some(expires) = cookie.expires {
+        json["expires"] = format_iso_time(expires).into();
+    }
+    json
+}
+
+fn netscape_line(cookie: &Cookie) -> String {
+    let bool_str = |b: bool| if b { "TRUE" } else { "FALSE" };
+    format!(
+        "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        if cookie.http_only { "#HttpOnly_" } else { "" },
+        cookie.host,
+        bool_str(cookie.host.starts_with('.')),
+        cookie.path,
+        bool_str(cookie.secure),
+        cookie.expires.unwrap_or(0),
+        cookie.name,
+        cookie.value
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jar() -> SiteCookie {
+        vec![
+            Cookie {
+                host: ".bing.com".to_string(),
+                path: "/".to_string(),
+                name: "sid".to_string(),
+                value: "a1".to_string(),
+                expires: Some(1700000000),
+                secure: true,
+                http_only: true,
+                same_site: SameSite::Lax,
+                ..Default::default()
+            },
+            Cookie {
+                host: "bing.com".to_string(),
+                path: "/search".to_string(),
+                name: "q".to_string(),
+                value: "b2".to_string(),
+                ..Default::default()
+            },
+        ]
+        .into()
+    }
+
+    #[test]
+    fn render_header_ok() {
+        assert_eq!(render(&jar(), Format::Header).unwrap(), "sid=a1; q=b2");
+    }
+
+    #[test]
+    fn render_netscape_ok() {
+        assert_eq!(
+            render(&jar(), Format::Netscape).unwrap(),
+            "# Netscape HTTP Cookie File\n\
+             #HttpOnly_.bing.com\tTRUE\t/\tTRUE\t1700000000\tsid\ta1\n\
+             bing.com\tFALSE\t/search\tFALSE\t0\tq\tb2\n"
+        );
+    }
+
+    #[test]
+    fn content_type_ok() {
+        for name in Format::NAMES {
+            let format: Format = name.parse().unwrap();
+            let text = render(&jar(), format).unwrap();
+            let json = serde_json::from_str::<serde_json::Value>(&text).is_ok();
+            assert_eq!(
+                format.content_type() == "application/json",
+                json,
+                "{}",
+                name
+            );
+        }
+        assert!(matches!(
+            "xml".parse::<Format>(),
+            Err(crate::Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn render_json_ok() {
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Json).unwrap()).unwrap();
+        assert_eq!(json[0]["sameSite"], "Lax");
+        assert_eq!(json[1]["expires"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn render_automation_ok() {
+        let state: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Playwright).unwrap()).unwrap();
+        assert_eq!(state["cookies"][0]["sameSite"], "Lax");
+        assert_eq!(state["cookies"][1]["expires"], -1);
+        assert_eq!(state["cookies"][1]["sameSite"], "Lax");
+        assert_eq!(state["origins"], serde_json::json!([]));
+
+        let puppeteer: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Puppeteer).unwrap()).unwrap();
+        assert_eq!(puppeteer[0]["expires"], 1700000000);
+        assert!(puppeteer[1].get("expires").is_none());
+        assert!(puppeteer[1].get("sameSite").is_none());
+
+        let selenium: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Selenium).unwrap()).unwrap();
+        assert_eq!(selenium[0]["expiry"], 1700000000);
+        assert_eq!(selenium[0]["httpOnly"], true);
+        assert!(selenium[1].get("expiry").is_none());
+    }
+
+    #[test]
+    fn render_http_clients_ok() {
+        let session: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Httpie).unwrap()).unwrap();
+        assert_eq!(session["cookies"][0]["domain"], ".bing.com");
+        assert_eq!(session["cookies"][1]["expires"], serde_json::Value::Null);
+
+        let postman: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Postman).unwrap()).unwrap();
+        assert_eq!(postman[0]["domain"], "bing.com");
+        assert_eq!(postman[0]["hostOnly"], false);
+        assert_eq!(postman[0]["expires"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(postman[1]["session"], true);
+
+        let insomnia: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Insomnia).unwrap()).unwrap();
+        let jar_cookies = &insomnia["resources"][0]["cookies"];
+        assert_eq!(jar_cookies[0]["key"], "sid");
+        assert_eq!(jar_cookies[1]["hostOnly"], true);
+
+        let har: serde_json::Value =
+            serde_json::from_str(&render(&jar(), Format::Har).unwrap()).unwrap();
+        assert_eq!(har[0]["expires"], "2023-11-14T22:13:20.000Z");
+        assert!(har[1].get("expires").is_none());
+    }
+}
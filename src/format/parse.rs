@@ -0,0 +1,475 @@
+//! Read cookies from what other tools write: cookie headers, `Set-Cookie` lines,
+//! `cookies.txt` files and the JSON of browser automation, HTTP clients and HAR captures.
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+
+use crate::browser::cookie::{Cookie, SameSite, SiteCookie};
+use crate::browser::utils::{get_site, parse_http_time, parse_iso_time};
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Guessed from the content, see [`InputFormat::detect`].
+    Auto,
+    /// `name=value; name2=value2`, with or without the `Cookie:` prefix.
+    Header,
+    /// One `Set-Cookie` header per line, with or without the `Set-Cookie:` prefix.
+    SetCookie,
+    /// Netscape `cookies.txt`.
+    Netscape,
+    /// Any JSON written by the output formats: an array of cookie objects, a Playwright
+    /// `storageState`, an HTTPie session, an Insomnia export or a HAR file.
+    Json,
+}
+
+impl InputFormat {
+    /// Every input format name accepted by [`InputFormat::from_str`]; the names of JSON
+    /// output formats all read as [`InputFormat::Json`].
+    pub const NAMES: &'static [&'static str] = &[
+        "auto",
+        "header",
+        "set-cookie",
+        "netscape",
+        "json",
+        "playwright",
+        "puppeteer",
+        "selenium",
+        "httpie",
+        "postman",
+        "insomnia",
+        "har",
+    ];
+
+    /// The format of `text`: JSON when it starts like JSON, `Set-Cookie` lines when one
+    /// line has the prefix, `cookies.txt` when a line has tabs, a header otherwise.
+    pub fn detect(text: &str) -> Self {
+        let text = text.trim_start();
+        if text.starts_with(['{', '[']) {
+            return InputFormat::Json;
+        }
+        let mut lines = text.lines().map(str::trim_end);
+        if lines
+            .clone()
+            .any(|line| strip_name(line, "set-cookie").is_some())
+        {
+            InputFormat::SetCookie
+        } else if lines.any(|line| line.starts_with("# Netscape") || line.contains('\t')) {
+            InputFormat::Netscape
+        } else {
+            InputFormat::Header
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(InputFormat::Auto),
+            "header" | "cookie" => Ok(InputFormat::Header),
+            "set-cookie" => Ok(InputFormat::SetCookie),
+            "netscape" | "cookies.txt" => Ok(InputFormat::Netscape),
+            "json" | "playwright" | "puppeteer" | "selenium" | "httpie" | "postman"
+            | "insomnia" | "har" => Ok(InputFormat::Json),
+            _ => Err(Error::Config(format!(
+                "unsupported input format {}; please use {}",
+                s,
+                InputFormat::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InputFormat::Auto => "auto",
+            InputFormat::Header => "header",
+            InputFormat::SetCookie => "set-cookie",
+            InputFormat::Netscape => "netscape",
+            InputFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parse cookies in the given format.
+///
+/// `host` is the host of cookies that do not name one, like those of a `Cookie` header or
+/// a `Set-Cookie` without `Domain`; a `Cookie` header needs it. Entries that cannot be read
+/// are skipped and listed in the warnings of the result; a document that cannot be read at
+/// all is an error.
+pub fn parse(text: &str, format: InputFormat, host: Option<&str>) -> Result<SiteCookie> {
+    match format {
+        InputFormat::Auto => parse(text, InputFormat::detect(text), host),
+        InputFormat::Header => match host.filter(|host| !host.is_empty()) {
+            Some(host) => Ok(parse_header(text, host)),
+            None => Err(Error::Parse(
+                "a Cookie header does not name its host; give the site".into(),
+            )),
+        },
+        InputFormat::SetCookie => Ok(parse_set_cookies(text, host.unwrap_or_default())),
+        InputFormat::Netscape => Ok(parse_netscape(text)),
+        InputFormat::Json => parse_json(text, host.unwrap_or_default()),
+    }
+}
+
+/// The rest of `line` after a `name:` prefix, compared case-insensitively.
+fn strip_name<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let (prefix, rest) = line.split_once(':')?;
+    prefix
+        .trim()
+        .eq_ignore_ascii_case(name)
+        .then_some(rest.trim_start())
+}
+
+fn new_cookie(host: &str, name: &str, value: &str) -> Cookie {
+    Cookie {
+        host: host.to_string(),
+        path: "/".to_string(),
+        name: name.trim().to_string(),
+        value: value.trim().to_string(),
+        ..Default::default()
+    }
+}
+
+/// Split `name=value`; a pair without `=` is a value with an empty name, as browsers read it.
+fn split_pair(pair: &str) -> (&str, &str) {
+    pair.split_once('=').unwrap_or(("", pair))
+}
+
+fn parse_header(text: &str, host: &str) -> SiteCookie {
+    let mut cookies = SiteCookie::new();
+    for line in text.lines() {
+        let line = strip_name(line, "cookie").unwrap_or(line);
+        for pair in line.split(';').filter(|pair| !pair.trim().is_empty()) {
+            let (name, value) = split_pair(pair);
+            cookies.push(new_cookie(host, name, value));
+        }
+    }
+    cookies
+}
+
+fn parse_same_site(value: &str) -> SameSite {
+    match value.trim().to_lowercase().as_str() {
+        "strict" => SameSite::Strict,
+        "lax" => SameSite::Lax,
+        // `no_restriction` is how Chrome extensions name None
+        "none" | "no_restriction" => SameSite::None,
+        _ => SameSite::Unspecified,
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+/// Parse one `Set-Cookie` value: a `Domain` attribute makes a `.domain` cookie, its
+/// absence a cookie of `host` only; `Max-Age` wins over `Expires`.
+fn parse_set_cookie(line: &str, host: &str) -> Cookie {
+    let (pair, attributes) = line.split_once(';').unwrap_or((line, ""));
+    let (name, value) = split_pair(pair);
+    let mut cookie = new_cookie(host, name, value);
+    let mut max_age = None;
+    for attribute in attributes.split(';') {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "domain" if !value.is_empty() => {
+                cookie.host = format!(".{}", value.trim_start_matches('.').to_lowercase())
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "expires" => cookie.expires = parse_http_time(value).or(cookie.expires),
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => cookie.same_site = parse_same_site(value),
+            _ => {}
+        }
+    }
+    if let Some(max_age) = max_age {
+        cookie.expires = Some(now() + max_age);
+    }
+    cookie
+}
+
+fn parse_set_cookies(text: &str, host: &str) -> SiteCookie {
+    let mut cookies = SiteCookie::new();
+    for line in text.lines() {
+        let line = strip_name(line, "set-cookie").unwrap_or(line);
+        if !line.trim().is_empty() {
+            cookies.push(parse_set_cookie(line, host));
+        }
+    }
+    cookies
+}
+
+/// Parse a `cookies.txt`: `domain subdomains path secure expires name value`, separated by
+/// tabs, with `#HttpOnly_` before the domain of HTTP-only cookies. The value is the rest of
+/// the line, tabs included.
+fn parse_netscape(text: &str) -> SiteCookie {
+    let mut cookies = SiteCookie::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.splitn(7, '\t').collect();
+        let [domain, subdomains, path, secure, expires, name, rest @ ..] = fields.as_slice() else {
+            cookies.push_warning(format!(
+                "line {}: expected 7 tab separated fields",
+                index + 1
+            ));
+            continue;
+        };
+        let mut cookie = new_cookie(domain, name, rest.first().unwrap_or(&""));
+        if subdomains.eq_ignore_ascii_case("TRUE") && !domain.starts_with('.') {
+            cookie.host = format!(".{}", domain);
+        }
+        cookie.path = path.to_string();
+        cookie.secure = secure.eq_ignore_ascii_case("TRUE");
+        cookie.expires = expires.parse::<i64>().ok().filter(|expires| *expires > 0);
+        cookie.http_only = http_only;
+        cookies.push(cookie);
+    }
+    cookies
+}
+
+/// Unix seconds of an expiry given as seconds (Playwright and Puppeteer use `-1` for
+/// session cookies), an ISO 8601 string or an HTTP date.
+fn json_time(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(number) => number.as_f64().map(|n| n as i64).filter(|n| *n > 0),
+        Value::String(text) => parse_iso_time(text).or_else(|| parse_http_time(text)),
+        _ => None,
+    }
+}
+
+/// Read a cookie object of any JSON output format; `host` is used when it has no domain.
+fn json_cookie(json: &Value, host: &str) -> Option<Cookie> {
+    let text = |key: &str| json.get(key).and_then(Value::as_str);
+    let flag = |key: &str| json.get(key).and_then(Value::as_bool);
+    // Insomnia calls the name `key`
+    let name = text("name").or_else(|| text("key"))?;
+    let mut cookie = new_cookie(text("domain").unwrap_or(host), name, text("value")?);
+    // Postman and Insomnia drop the dot of domain cookies and mark the others `hostOnly`
+    if flag("hostOnly") == Some(false) && !cookie.host.starts_with('.') {
+        cookie.host.insert(0, '.');
+    }
+    if let Some(path) = text("path") {
+        cookie.path = path.to_string();
+    }
+    cookie.expires = ["expires", "expiry", "expirationDate"]
+        .iter()
+        .find_map(|key| json.get(*key))
+        .and_then(json_time)
+        .filter(|_| flag("session") != Some(true));
+    cookie.secure = flag("secure").unwrap_or_default();
+    cookie.http_only = flag("httpOnly").unwrap_or_default();
+    cookie.same_site = text("sameSite").map_or(SameSite::Unspecified, parse_same_site);
+    cookie.creation = json.get("creation").and_then(json_time);
+    cookie.last_access = json.get("lastAccessed").and_then(json_time);
+    Some(cookie)
+}
+
+fn push_json_cookies(cookies: &mut SiteCookie, list: &Value, host: &str) {
+    for (index, json) in list.as_array().into_iter().flatten().enumerate() {
+        match json_cookie(json, host) {
+            Some(cookie) => cookies.push(cookie),
+            None => cookies.push_warning(format!("cookie {}: no name or value", index + 1)),
+        }
+    }
+}
+
+fn parse_json(text: &str, host: &str) -> Result<SiteCookie> {
+    let json: Value = serde_json::from_str(text).map_err(|err| Error::Parse(err.to_string()))?;
+    let mut cookies = SiteCookie::new();
+    if json.is_array() {
+        push_json_cookies(&mut cookies, &json, host);
+    } else if let Some(entries) = json.pointer("/log/entries").and_then(Value::as_array) {
+        // HAR cookies have no domain unless the tool adds one; use the host of the request
+        for entry in entries {
+            let request_host = entry
+                .pointer("/request/url")
+                .and_then(Value::as_str)
+                .and_then(|url| get_site(url).ok());
+            let host = request_host.as_deref().unwrap_or(host);
+            for message in ["request", "response"] {
+                if let Some(list) = entry.get(message).and_then(|m| m.get("cookies")) {
+                    push_json_cookies(&mut cookies, list, host);
+                }
+            }
+        }
+    } else if let Some(list) = json.get("cookies").filter(|list| list.is_array()) {
+        // a Playwright storageState or an HTTPie session
+        push_json_cookies(&mut cookies, list, host);
+    } else if let Some(resources) = json.get("resources").and_then(Value::as_array) {
+        // an Insomnia export: the cookie jars among its resources
+        for resource in resources {
+            if let Some(list) = resource.get("cookies") {
+                push_json_cookies(&mut cookies, list, host);
+            }
+        }
+    } else {
+        return Err(Error::Parse(
+            "expected an array of cookies, a HAR file or an object with cookies".into(),
+        ));
+    }
+    Ok(cookies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{render, Format};
+
+    fn jar() -> SiteCookie {
+        vec![
+            Cookie {
+                host: ".bing.com".into(),
+                path: "/".into(),
+                name: "sid".into(),
+                value: "a1".into(),
+                expires: Some(1_700_000_000),
+                secure: true,
+                http_only: true,
+                ..Default::default()
+            },
+            Cookie {
+                host: "www.bing.com".into(),
+                path: "/search".into(),
+                name: "q".into(),
+                value: "b=2".into(),
+                ..Default::default()
+            },
+        ]
+        .into()
+    }
+
+    /// The fields every format keeps.
+    fn basic(cookies: &SiteCookie) -> Vec<(String, String, String, String, Option<i64>)> {
+        cookies
+            .iter()
+            .map(|c| {
+                let host = c.host.clone();
+                (
+                    host,
+                    c.path.clone(),
+                    c.name.clone(),
+                    c.value.clone(),
+                    c.expires,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_roundtrip_ok() {
+        for format in [
+            Format::Json,
+            Format::Netscape,
+            Format::Playwright,
+            Format::Puppeteer,
+            Format::Selenium,
+            Format::Httpie,
+            Format::Postman,
+            Format::Insomnia,
+            Format::Har,
+        ] {
+            let text = render(&jar(), format).unwrap();
+            let cookies = parse(&text, InputFormat::Auto, None).unwrap();
+            assert_eq!(basic(&cookies), basic(&jar()), "{}", format);
+            assert!(cookies.warnings().is_empty());
+        }
+        let cookies = parse(
+            &render(&jar(), Format::Netscape).unwrap(),
+            InputFormat::Netscape,
+            None,
+        )
+        .unwrap();
+        assert!(cookies[0].http_only && cookies[0].secure);
+        let cookies = parse(
+            ".bing.com\tTRUE\t/\tFALSE\t0\tsid\ta\tb",
+            InputFormat::Netscape,
+            None,
+        )
+        .unwrap();
+        assert_eq!(cookies[0].value, "a\tb");
+    }
+
+    #[test]
+    fn parse_headers_ok() {
+        let cookies = parse(
+            "Cookie: a=1; b=x=y; ;c",
+            InputFormat::Auto,
+            Some("bing.com"),
+        )
+        .unwrap();
+        let pairs: Vec<_> = cookies
+            .iter()
+            .map(|c| (c.host.as_str(), c.name.as_str(), c.value.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("bing.com", "a", "1"),
+                ("bing.com", "b", "x=y"),
+                ("bing.com", "", "c")
+            ]
+        );
+
+        let text = "Set-Cookie: sid=a1; Domain=Bing.com; Path=/; Expires=Tue, 14 Nov 2023 22:13:20 GMT; Secure; HttpOnly; SameSite=Strict\nset-cookie: q=2\n";
+        assert_eq!(InputFormat::detect(text), InputFormat::SetCookie);
+        let cookies = parse(text, InputFormat::Auto, Some("www.bing.com")).unwrap();
+        assert_eq!(cookies[0].host, ".bing.com");
+        assert_eq!(cookies[0].expires, Some(1_700_000_000));
+        assert!(cookies[0].secure && cookies[0].http_only);
+        assert_eq!(cookies[0].same_site, SameSite::Strict);
+        assert_eq!(
+            (cookies[1].host.as_str(), cookies[1].expires),
+            ("www.bing.com", None)
+        );
+        let cookies = parse("a=1; Max-Age=60", InputFormat::SetCookie, None).unwrap();
+        assert!(cookies[0].expires.unwrap() > now());
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(matches!(
+            parse("{\"a\": 1}", InputFormat::Json, None),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            parse("[", InputFormat::Auto, None),
+            Err(Error::Parse(_))
+        ));
+        let cookies = parse("bing.com\tTRUE\t/\n", InputFormat::Netscape, None).unwrap();
+        assert!(cookies.is_empty());
+        assert_eq!(cookies.warnings().len(), 1);
+        assert!(matches!(
+            parse("a=1; b=2", InputFormat::Header, None),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            parse("Cookie: a=1", InputFormat::Auto, Some("")),
+            Err(Error::Parse(_))
+        ));
+        assert_eq!("HAR".parse::<InputFormat>().unwrap(), InputFormat::Json);
+        assert!(matches!(
+            "xml".parse::<InputFormat>(),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,428 @@
+//! User-defined output templates.
+//!
+//! ```text
+//! {{#cookies separator="\n"}}{{name}}={{value | shell}}{{#if secure}} # secure{{/if}}{{/cookies}}
+//! ```
+//!
+//! - `{{#cookies}}...{{/cookies}}` repeats its body for every cookie, joined by the
+//!   optional `separator` (`\n` and `\t` are unescaped).
+//! - Cookie placeholders: `name`, `value`, `domain`, `path`, `expires` (Unix seconds),
+//!   `expires_iso`, `same_site`, `index` and the flags `secure`, `http_only`, `host_only`
+//!   and `session`.
+//! - Placeholders of the whole jar, usable anywhere: `header` (`a=1; b=2`) and `count`.
+//! - Filters escape a value: `shell` (single-quoted), `json` (a quoted JSON string) and
+//!   `url` (percent-encoded).
+//! - `{{#if flag}}...{{else}}...{{/if}}` and `{{#unless flag}}...{{/unless}}` test a flag,
+//!   or whether a placeholder is non-empty.
+use std::{fs, path::Path};
+
+use crate::browser::cookie::{Cookie, SameSite, SiteCookie};
+use crate::browser::utils::format_iso_time;
+use crate::{Error, Result};
+
+const COOKIE_VARS: &[&str] = &[
+    "name",
+    "value",
+    "domain",
+    "path",
+    "expires",
+    "expires_iso",
+    "same_site",
+    "index",
+    "secure",
+    "http_only",
+    "host_only",
+    "session",
+];
+const JAR_VARS: &[&str] = &["header", "count"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Shell,
+    Json,
+    Url,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        filters: Vec<Filter>,
+    },
+    Cookies {
+        separator: String,
+        body: Vec<Node>,
+    },
+    If {
+        negate: bool,
+        name: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// A parsed template, rendered once per cookie jar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Value {
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn is_true(&self) -> bool {
+        match self {
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+        }
+    }
+
+    fn into_string(self) -> String {
+        match self {
+            Value::Str(s) => s,
+            Value::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+/// What a block is closed by, to parse nested blocks.
+#[derive(PartialEq, Eq)]
+enum End {
+    Eof,
+    Cookies,
+    If,
+    Unless,
+    Else,
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::Template(msg.into())
+}
+
+impl<'a> Parser<'a> {
+    /// Parse nodes up to the closing tag of the enclosing block.
+    fn nodes(&mut self, in_cookies: bool) -> Result<(Vec<Node>, End)> {
+        let mut nodes = vec![];
+        loop {
+            let Some(start) = self.rest.find("{{") else {
+                if !self.rest.is_empty() {
+                    nodes.push(Node::Text(self.rest.to_string()));
+                }
+                self.rest = "";
+                return Ok((nodes, End::Eof));
+            };
+            if start > 0 {
+                nodes.push(Node::Text(self.rest[..start].to_string()));
+            }
+            let end = self.rest[start..]
+                .find("}}")
+                .ok_or_else(|| invalid("unclosed {{"))?;
+            let tag = self.rest[start + 2..start + end].trim();
+            self.rest = &self.rest[start + end + 2..];
+            if let Some(block) = tag.strip_prefix('#') {
+                nodes.push(self.block(block.trim(), in_cookies)?);
+            } else if let Some(closed) = tag.strip_prefix('/') {
+                return match closed.trim() {
+                    "cookies" => Ok((nodes, End::Cookies)),
+                    "if" => Ok((nodes, End::If)),
+                    "unless" => Ok((nodes, End::Unless)),
+                    other => Err(invalid(format!("unknown closing tag {{{{/{}}}}}", other))),
+                };
+            } else if tag == "else" {
+                return Ok((nodes, End::Else));
+            } else {
+                nodes.push(var(tag, in_cookies)?);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &str, in_cookies: bool) -> Result<Node> {
+        let (kind, args) = block.split_once(' ').unwrap_or((block, ""));
+        match kind {
+            "cookies" => {
+                if in_cookies {
+                    return Err(invalid("{{#cookies}} cannot be nested"));
+                }
+                let separator = separator(args.trim())?;
+                match self.nodes(true)? {
+                    (body, End::Cookies) => Ok(Node::Cookies { separator, body }),
+                    _ => Err(invalid("{{#cookies}} is not closed by {{/cookies}}")),
+                }
+            }
+            "if" | "unless" => {
+                let name = args.trim().to_string();
+                check_var(&name, in_cookies)?;
+                let close = if kind == "if" { End::If } else { End::Unless };
+                let not_closed = || {
+                    invalid(format!(
+                        "{{{{#{}}}}} is not closed by {{{{/{}}}}}",
+                        kind, kind
+                    ))
+                };
+                let (then, end) = self.nodes(in_cookies)?;
+                let otherwise = match end {
+                    End::Else => match self.nodes(in_cookies)? {
+                        (otherwise, end) if end == close => otherwise,
+                        _ => return Err(not_closed()),
+                    },
+                    end if end == close => vec![],
+                    _ => return Err(not_closed()),
+                };
+                Ok(Node::If {
+                    negate: kind == "unless",
+                    name,
+                    then,
+                    otherwise,
+                })
+            }
+            _ => Err(invalid(format!("unknown block {{{{#{}}}}}", kind))),
+        }
+    }
+}
+
+/// `separator="..."`, with `\n`, `\t`, `\"` and `\\` unescaped.
+fn separator(args: &str) -> Result<String> {
+    if args.is_empty() {
+        return Ok(String::new());
+    }
+    let quoted = args
+        .strip_prefix("separator=")
+        .and_then(|s| s.strip_prefix('"'))
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| invalid(format!("expected separator=\"...\", found {}", args)))?;
+    let mut out = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    Ok(out)
+}
+
+fn check_var(name: &str, in_cookies: bool) -> Result<()> {
+    if JAR_VARS.contains(&name) || (in_cookies && COOKIE_VARS.contains(&name)) {
+        Ok(())
+    } else if COOKIE_VARS.contains(&name) {
+        Err(invalid(format!(
+            "{} is only known inside {{{{#cookies}}}}",
+            name
+        )))
+    } else {
+        Err(invalid(format!("unknown placeholder {}", name)))
+    }
+}
+
+/// `name | filter | filter`.
+fn var(tag: &str, in_cookies: bool) -> Result<Node> {
+    let mut parts = tag.split('|').map(str::trim);
+    let name = parts.next().unwrap_or_default().to_string();
+    check_var(&name, in_cookies)?;
+    let filters = parts
+        .map(|filter| match filter {
+            "shell" => Ok(Filter::Shell),
+            "json" => Ok(Filter::Json),
+            "url" => Ok(Filter::Url),
+            _ => Err(invalid(format!(
+                "unknown filter {}; please use shell, json or url",
+                filter
+            ))),
+        })
+        .collect::<Result<_>>()?;
+    Ok(Node::Var { name, filters })
+}
+
+fn apply(filter: Filter, value: String) -> String {
+    match filter {
+        Filter::Shell => format!("'{}'", value.replace('\'', r"'\''")),
+        Filter::Json => serde_json::Value::String(value).to_string(),
+        Filter::Url => value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect(),
+    }
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { rest: text };
+        match parser.nodes(false)? {
+            (nodes, End::Eof) => Ok(Self { nodes }),
+            (_, End::Else) => Err(invalid("{{else}} outside of {{#if}}")),
+            (_, _) => Err(invalid("closing tag without a block")),
+        }
+    }
+
+    /// A template given on the command line: `@path` reads it from a file.
+    pub fn load(template: &str) -> Result<Self> {
+        match template.strip_prefix('@') {
+            Some(path) => {
+                let text = fs::read_to_string(Path::new(path))
+                    .map_err(|_| Error::NotFound(path.to_string()))?;
+                Self::parse(&text)
+            }
+            None => Self::parse(template),
+        }
+    }
+
+    pub fn render(&self, cookies: &SiteCookie) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, cookies, None, &mut out);
+        out
+    }
+}
+
+fn lookup(name: &str, cookies: &SiteCookie, cookie: Option<(usize, &Cookie)>) -> Value {
+    match (name, cookie) {
+        ("header", _) => Value::Str(cookies.to_string()),
+        ("count", _) => Value::Str(cookies.len().to_string()),
+        (_, None) => Value::Str(String::new()),
+        (name, Some((index, cookie))) => match name {
+            "name" => Value::Str(cookie.name.clone()),
+            "value" => Value::Str(cookie.value.clone()),
+            "domain" => Value::Str(cookie.host.clone()),
+            "path" => Value::Str(cookie.path.clone()),
+            "expires" => Value::Str(cookie.expires.map(|t| t.to_string()).unwrap_or_default()),
+            "expires_iso" => Value::Str(cookie.expires.map(format_iso_time).unwrap_or_default()),
+            "same_site" => Value::Str(match cookie.same_site {
+                SameSite::Unspecified => String::new(),
+                same_site => same_site.to_string(),
+            }),
+            "index" => Value::Str(index.to_string()),
+            "secure" => Value::Bool(cookie.secure),
+            "http_only" => Value::Bool(cookie.http_only),
+            "host_only" => Value::Bool(!cookie.host.starts_with('.')),
+            "session" => Value::Bool(cookie.expires.is_none()),
+            _ => Value::Str(String::new()),
+        },
+    }
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    cookies: &SiteCookie,
+    cookie: Option<(usize, &Cookie)>,
+    out: &mut String,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { name, filters } => {
+                let value = lookup(name, cookies, cookie).into_string();
+                out.push_str(&filters.iter().fold(value, |v, f| apply(*f, v)));
+            }
+            Node::Cookies { separator, body } => {
+                for (index, each) in cookies.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(separator);
+                    }
+                    render_nodes(body, cookies, Some((index, each)), out);
+                }
+            }
+            Node::If {
+                negate,
+                name,
+                then,
+                otherwise,
+            } => {
+                let branch = if lookup(name, cookies, cookie).is_true() != *negate {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, cookies, cookie, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jar() -> SiteCookie {
+        vec![
+            Cookie {
+                host: ".bing.com".to_string(),
+                path: "/".to_string(),
+                name: "sid".to_string(),
+                value: "it's".to_string(),
+                secure: true,
+                expires: Some(1700000000),
+                ..Default::default()
+            },
+            Cookie {
+                host: "bing.com".to_string(),
+                path: "/".to_string(),
+                name: "q".to_string(),
+                value: "a b&c".to_string(),
+                ..Default::default()
+            },
+        ]
+        .into()
+    }
+
+    #[test]
+    fn render_template_ok() {
+        let render = |text: &str| Template::parse(text).unwrap().render(&jar());
+        assert_eq!(render("Cookie: {{header}}"), "Cookie: sid=it's; q=a b&c");
+        assert_eq!(
+            render(r#"{{#cookies separator="\n"}}{{name}}={{value | shell}}{{/cookies}}"#),
+            "sid='it'\\''s'\nq='a b&c'"
+        );
+        assert_eq!(
+            render("{{#cookies}}{{value | url}};{{/cookies}}"),
+            "it%27s;a%20b%26c;"
+        );
+        assert_eq!(
+            render(r#"[{{#cookies separator=","}}{{name | json}}{{/cookies}}]"#),
+            r#"["sid","q"]"#
+        );
+        assert_eq!(
+            render("{{#cookies}}{{#if secure}}S{{else}}-{{/if}}{{#unless expires}}s{{/unless}}{{/cookies}} {{count}}"),
+            "S-s 2"
+        );
+    }
+
+    #[test]
+    fn parse_template_err() {
+        for text in [
+            "{{name}}",
+            "{{#cookies}}{{nmae}}{{/cookies}}",
+            "{{#cookies}}{{value | base64}}{{/cookies}}",
+            "{{#cookies}}",
+            "{{#if secure}}{{/if}}",
+            "{{header",
+            "{{/cookies}}",
+            "{{else}}",
+            "{{#cookies}}{{#if secure}}S{{/unless}}{{/cookies}}",
+            "{{#cookies}}{{#unless secure}}S{{else}}-{{/if}}{{/cookies}}",
+        ] {
+            assert!(
+                matches!(Template::parse(text), Err(Error::Template(_))),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -1,8 +1,16 @@
 pub mod browser;
+pub mod config;
+mod error;
+pub mod format;
+pub mod snapshot;
+pub mod vault;
 
 #[cfg(target_os = "windows")]
 pub mod windows;
 
+pub use error::{Error, Result};
+
 pub use browser::get_cookies;
 pub use browser::get_firefox_cookies_by_path;
-pub use browser::get_chrome_cookies_by_path;
\ No newline at end of file
+pub use browser::get_chrome_cookies_by_path;
+pub use browser::spec::BrowserSpec;
\ No newline at end of file
@@ -1,55 +1,51 @@
-use clap::{arg, crate_version, Command};
-use gcookie::browser::{gcookie_chrome_by_path, get_cookies, get_firefox_cookies_by_path};
-use std::{error::Error, path::PathBuf};
+use clap::{crate_version, ArgMatches, Command};
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+mod cmd;
+use cmd::MyResult;
 
 fn main() {
-    if let Err(err) = run() {
-        eprintln!("{}", err);
-        std::process::exit(1);
+    let matches = build_app().get_matches();
+    if let Err(err) = run(&matches) {
+        std::process::exit(cmd::exit::report(&matches, err.as_ref()));
     }
 }
-fn run() -> MyResult<()> {
-    let app = build_app();
-    let matches = app.get_matches();
-    let site = matches.get_one::<String>("site").unwrap();
-    let firefox = matches.get_one::<PathBuf>("firefox");
-    if firefox.is_some() {
-        let res = get_firefox_cookies_by_path(site, firefox.unwrap())?;
-        print!("{}", res);
-        return Ok(());
+fn run(matches: &ArgMatches) -> MyResult<()> {
+    match matches.subcommand() {
+        Some(("get", sub)) => cmd::get::run(sub),
+        Some(("export", sub)) => cmd::export::run(sub),
+        Some(("convert", sub)) => cmd::convert::run(sub),
+        Some(("import", sub)) => cmd::import::run(sub),
+        Some(("copy", sub)) => cmd::copy::run(sub),
+        Some(("delete", sub)) => cmd::delete::run(sub),
+        Some(("domains", sub)) => cmd::domains::run(sub),
+        Some(("doctor", sub)) => cmd::doctor::run(sub),
+        Some(("profiles", sub)) => cmd::profiles::run(sub),
+        Some(("serve", sub)) => cmd::serve::run(sub),
+        Some(("snapshot", sub)) => cmd::snapshot::run(sub),
+        Some(("vault", sub)) => cmd::vault::run(sub),
+        // `gcookie <site>` is short for `gcookie get <site>`
+        _ => cmd::get::run(matches),
     }
-    let chrome_path = matches.get_one::<PathBuf>("chrome_path");
-    if let Some(p) = chrome_path {
-        let res = gcookie_chrome_by_path(site, p)?;
-        print!("{}", res);
-        return Ok(());
-    }
-    let browser = matches.get_one::<String>("chrome").map(|s| s.as_str());
-    let res = get_cookies(browser.unwrap(), site)?;
-    print!("{}", res);
-    Ok(())
 }
 
 fn build_app() -> Command {
-    let app = Command::new("gcookie")
+    Command::new("gcookie")
         .version(crate_version!())
         .about("get site cookie string")
-        .arg(
-            arg!(-c --chrome [chrome] "Browser's name. Chrome, Chromium, Edge or Firefox is OK.")
-                .default_value("Chrome"),
-        )
-        .arg(
-            arg!(chrome_path: -p --"chrome-path" [chrome_path] "the use data path of Chrome")
-                .value_parser(clap::value_parser!(PathBuf))
-                .conflicts_with("firefox"),
-        )
-        .arg(
-            arg!(firefox: -f --firefox [firefox] "path of firefox profile")
-                .value_parser(clap::value_parser!(PathBuf)),
-        )
-        .arg(arg!(<site> "URL of the site or host of the site"));
-
-    app
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .arg(cmd::config_arg())
+        .args(cmd::get::args())
+        .subcommand(cmd::get::command())
+        .subcommand(cmd::export::command())
+        .subcommand(cmd::convert::command())
+        .subcommand(cmd::import::command())
+        .subcommand(cmd::copy::command())
+        .subcommand(cmd::delete::command())
+        .subcommand(cmd::snapshot::command())
+        .subcommand(cmd::vault::command())
+        .subcommand(cmd::serve::command())
+        .subcommand(cmd::profiles::command())
+        .subcommand(cmd::domains::command())
+        .subcommand(cmd::doctor::command())
 }
@@ -0,0 +1,442 @@
+//! Saved copies of the cookies of a browser profile, restored later through the import
+//! writers into the same or another profile.
+//!
+//! A snapshot is a JSON file holding the cookies with their values encrypted with
+//! AES-256-GCM under a key that gcookie keeps next to the snapshots, so the file is no
+//! more readable than the cookie database it came from.
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::browser::cookie::{Cookie, SameSite, SiteCookie};
+use crate::browser::load_cookies_by_queries;
+use crate::browser::source::Query;
+use crate::browser::spec::{BrowserSpec, ProfileRef};
+use crate::{Error, Result};
+
+const VERSION: u32 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Cookies of a profile at one point in time.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub name: String,
+    /// Unix seconds when the snapshot was taken.
+    pub created: i64,
+    /// Spec of the profile the cookies were read from, with its resolved path.
+    pub source: String,
+    /// Sites the snapshot was limited to; every cookie of the profile when empty.
+    pub sites: Vec<String>,
+    /// Cookie names the snapshot was limited to; every name when empty.
+    pub names: Vec<String>,
+    pub cookies: SiteCookie,
+}
+
+impl Snapshot {
+    /// Read the cookies of `sites` from the profile named by `spec`, or all of them when
+    /// `sites` is empty.
+    pub fn take(
+        name: &str,
+        spec: &BrowserSpec,
+        sites: Vec<String>,
+        names: Vec<String>,
+    ) -> Result<Self> {
+        let (_, path) = spec.resolve()?;
+        let mut source = spec.clone();
+        source.profile = Some(ProfileRef::Path(path));
+        let mut snapshot = Snapshot {
+            name: name.to_string(),
+            created: crate::browser::import::now(),
+            source: source.to_string(),
+            sites,
+            names,
+            cookies: SiteCookie::new(),
+        };
+        snapshot.cookies = load_cookies_by_queries(spec, &snapshot.queries()?)?;
+        Ok(snapshot)
+    }
+
+    /// The queries selecting the cookies of the snapshot in a profile.
+    pub fn queries(&self) -> Result<Vec<Query>> {
+        if self.sites.is_empty() {
+            return Ok(vec![Query::all().with_names(self.names.clone())]);
+        }
+        self.sites
+            .iter()
+            .map(|site| Ok(Query::site(site)?.with_names(self.names.clone())))
+            .collect()
+    }
+
+    /// Hosts with cookies in the snapshot.
+    pub fn hosts(&self) -> usize {
+        let mut hosts: Vec<&str> = self.cookies.iter().map(|c| c.host.as_str()).collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+        hosts.len()
+    }
+}
+
+/// The directory of saved snapshots, with the key encrypting their values.
+#[derive(Debug, Clone)]
+pub struct Snapshots {
+    dir: PathBuf,
+}
+
+impl Snapshots {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// `$XDG_DATA_HOME/gcookie/snapshots`, or `gcookie/snapshots` in the platform data
+    /// directory.
+    pub fn default_dir() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(dirs::data_dir)?;
+        Some(dir.join("gcookie").join("snapshots"))
+    }
+
+    /// The snapshots in [`Snapshots::default_dir`].
+    pub fn find() -> Result<Self> {
+        Self::default_dir()
+            .map(Self::new)
+            .ok_or_else(|| Error::NotFound("data directory".into()))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(['/', '\\'])
+            && !name.chars().any(char::is_control);
+        if !valid {
+            return Err(Error::Config(format!("invalid snapshot name {:?}", name)));
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    /// Write a snapshot, replacing one of the same name only with `overwrite`.
+    pub fn save(&self, snapshot: &Snapshot, overwrite: bool) -> Result<PathBuf> {
+        let path = self.path(&snapshot.name)?;
+        if path.exists() && !overwrite {
+            return Err(Error::Config(format!(
+                "snapshot {} exists; use --force to replace it",
+                snapshot.name
+            )));
+        }
+        let key = self.key(true)?;
+        let cookies = snapshot
+            .cookies
+            .iter()
+            .map(|cookie| StoredCookie::seal(cookie, &key))
+            .collect();
+        let stored = StoredSnapshot {
+            version: VERSION,
+            name: snapshot.name.clone(),
+            created: snapshot.created,
+            source: snapshot.source.clone(),
+            sites: snapshot.sites.clone(),
+            names: snapshot.names.clone(),
+            cookies,
+        };
+        let json = serde_json::to_string_pretty(&stored).unwrap_or_default();
+        write_private(&path, json.as_bytes(), true)?;
+        Ok(path)
+    }
+
+    /// Read a snapshot and decrypt its values.
+    pub fn load(&self, name: &str) -> Result<Snapshot> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Err(Error::NotFound(format!("snapshot {}", name)));
+        }
+        let stored = read_stored(&path)?;
+        let key = self.key(false)?;
+        let mut cookies = SiteCookie::new();
+        for cookie in &stored.cookies {
+            cookies.push(cookie.open(&key).ok_or_else(|| {
+                Error::DecryptFailed(format!("{} of snapshot {}", cookie.name, name))
+            })?);
+        }
+        Ok(Snapshot {
+            name: stored.name,
+            created: stored.created,
+            source: stored.source,
+            sites: stored.sites,
+            names: stored.names,
+            cookies,
+        })
+    }
+
+    /// Every saved snapshot, oldest first, with its values left encrypted; files that
+    /// cannot be read, or are of another version, are left out with a warning.
+    pub fn list(&self) -> Result<(Vec<Snapshot>, Vec<String>)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Ok((vec![], vec![]));
+        };
+        let mut snapshots = vec![];
+        let mut warnings = vec![];
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let stored = match read_stored(&path) {
+                Ok(stored) => stored,
+                Err(Error::Io(err)) => {
+                    warnings.push(format!("skipped {}: {}", path.display(), err));
+                    continue;
+                }
+                Err(err) => {
+                    warnings.push(format!("skipped {}", err));
+                    continue;
+                }
+            };
+            let cookies: Vec<Cookie> = stored
+                .cookies
+                .iter()
+                .map(|cookie| cookie.metadata())
+                .collect();
+            snapshots.push(Snapshot {
+                name: stored.name,
+                created: stored.created,
+                source: stored.source,
+                sites: stored.sites,
+                names: stored.names,
+                cookies: cookies.into(),
+            });
+        }
+        snapshots.sort_by(|a, b| (a.created, &a.name).cmp(&(b.created, &b.name)));
+        Ok((snapshots, warnings))
+    }
+
+    /// The key of the snapshot values, created on the first save.
+    fn key(&self, create: bool) -> Result<Vec<u8>> {
+        let path = self.dir.join("key");
+        match fs::read(&path) {
+            Ok(key) if key.len() == 32 => Ok(key),
+            Ok(_) => Err(Error::KeyUnavailable(format!(
+                "{} is not a snapshot key",
+                path.display()
+            ))),
+            Err(_) if create => {
+                let key = Aes256Gcm::generate_key(OsRng).to_vec();
+                write_private(&path, &key, false)?;
+                Ok(key)
+            }
+            Err(_) => Err(Error::KeyUnavailable(format!(
+                "{} is missing",
+                path.display()
+            ))),
+        }
+    }
+}
+
+fn read_stored(path: &Path) -> Result<StoredSnapshot> {
+    let text = fs::read_to_string(path)?;
+    let stored: StoredSnapshot = serde_json::from_str(&text)
+        .map_err(|err| Error::Schema(format!("{}: {}", path.display(), err)))?;
+    if stored.version != VERSION {
+        return Err(Error::Schema(format!(
+            "{}: snapshot version {}",
+            path.display(),
+            stored.version
+        )));
+    }
+    Ok(stored)
+}
+
+/// Write a file readable only by its owner, creating its directory; an existing file is
+/// only replaced with `overwrite`.
+pub(crate) fn write_private(path: &Path, content: &[u8], overwrite: bool) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(content)?;
+    Ok(())
+}
+
+/// Encrypt with AES-256-GCM under a random nonce, authenticating `aad` too; returns the
+/// nonce followed by the ciphertext.
+pub(crate) fn seal(key: &[u8], aad: &[u8], plain: &[u8]) -> Vec<u8> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let encrypted = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad })
+        .unwrap_or_default();
+    [nonce.as_slice(), &encrypted].concat()
+}
+
+/// Decrypt the output of [`seal`]; `None` when the key or `aad` differ or it was altered.
+pub(crate) fn open(key: &[u8], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, encrypted) = sealed.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: encrypted,
+                aad,
+            },
+        )
+        .ok()
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSnapshot {
+    version: u32,
+    name: String,
+    created: i64,
+    source: String,
+    sites: Vec<String>,
+    #[serde(default)]
+    names: Vec<String>,
+    cookies: Vec<StoredCookie>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredCookie {
+    host: String,
+    path: String,
+    name: String,
+    /// Base64 of the nonce and the encrypted value.
+    value: String,
+    expires: Option<i64>,
+    secure: bool,
+    http_only: bool,
+    same_site: String,
+    creation: Option<i64>,
+    last_access: Option<i64>,
+}
+
+impl StoredCookie {
+    fn seal(cookie: &Cookie, key: &[u8]) -> Self {
+        let aad = aad(&cookie.host, &cookie.path, &cookie.name);
+        Self {
+            host: cookie.host.clone(),
+            path: cookie.path.clone(),
+            name: cookie.name.clone(),
+            value: STANDARD.encode(seal(key, &aad, cookie.value.as_bytes())),
+            expires: cookie.expires,
+            secure: cookie.secure,
+            http_only: cookie.http_only,
+            same_site: cookie.same_site.to_string(),
+            creation: cookie.creation,
+            last_access: cookie.last_access,
+        }
+    }
+
+    /// The cookie without its value.
+    fn metadata(&self) -> Cookie {
+        Cookie {
+            host: self.host.clone(),
+            path: self.path.clone(),
+            name: self.name.clone(),
+            expires: self.expires,
+            secure: self.secure,
+            http_only: self.http_only,
+            same_site: match self.same_site.as_str() {
+                "None" => SameSite::None,
+                "Lax" => SameSite::Lax,
+                "Strict" => SameSite::Strict,
+                _ => SameSite::Unspecified,
+            },
+            creation: self.creation,
+            last_access: self.last_access,
+            ..Default::default()
+        }
+    }
+
+    fn open(&self, key: &[u8]) -> Option<Cookie> {
+        let sealed = STANDARD.decode(&self.value).ok()?;
+        let value = open(key, &aad(&self.host, &self.path, &self.name), &sealed)?;
+        Some(Cookie {
+            value: String::from_utf8(value).ok()?,
+            ..self.metadata()
+        })
+    }
+}
+
+/// Binds an encrypted value to its cookie, so values cannot be swapped between cookies.
+fn aad(host: &str, path: &str, name: &str) -> Vec<u8> {
+    [host, path, name].join("\n").into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshots = Snapshots::new(dir.path().to_path_buf());
+        let cookie = Cookie {
+            host: ".bing.com".into(),
+            path: "/".into(),
+            name: "sid".into(),
+            value: "secret".into(),
+            expires: Some(1_800_000_000),
+            secure: true,
+            same_site: SameSite::Lax,
+            ..Default::default()
+        };
+        let snapshot = Snapshot {
+            name: "before".into(),
+            created: 1_700_000_000,
+            source: "firefox:/tmp/xyz.work".into(),
+            sites: vec!["bing.com".into()],
+            cookies: vec![cookie.clone()].into(),
+            ..Default::default()
+        };
+        let path = snapshots.save(&snapshot, false).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+        assert!(matches!(
+            snapshots.save(&snapshot, false),
+            Err(Error::Config(_))
+        ));
+        assert_eq!(snapshots.load("before").unwrap().cookies[0], cookie);
+
+        fs::write(dir.path().join("old.json"), r#"{"version": 0}"#).unwrap();
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        let (listed, warnings) = snapshots.list().unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].cookies[0].value, "");
+        assert_eq!(listed[0].hosts(), 1);
+
+        // a value moved to another cookie does not decrypt
+        let tampered = fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"sid\"", "\"uid\"");
+        fs::write(&path, tampered).unwrap();
+        assert!(matches!(
+            snapshots.load("before"),
+            Err(Error::DecryptFailed(_))
+        ));
+        assert!(matches!(snapshots.load("../key"), Err(Error::Config(_))));
+        assert!(matches!(snapshots.load("after"), Err(Error::NotFound(_))));
+    }
+}
@@ -0,0 +1,377 @@
+//! Portable encrypted cookie files, for machines without a browser.
+//!
+//! A vault holds cookies exported on a workstation, encrypted with AES-256-GCM under a
+//! key derived with PBKDF2-HMAC-SHA256 from a passphrase or a key file. It is read back
+//! like a browser, with the spec `vault:/path/to/file`.
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::browser::cookie::{Cookie, SiteCookie};
+use crate::browser::source::{CookieSource, CookieStore, Query};
+use crate::format::parse::{parse, InputFormat};
+use crate::snapshot::{open, seal, write_private};
+use crate::{Error, Result};
+
+/// Browser name of vaults in specs.
+pub const NAME: &str = "vault";
+const VERSION: u32 = 1;
+/// PBKDF2 rounds for passphrases; key files hold random bytes and need one.
+const PASSPHRASE_ROUNDS: u32 = 600_000;
+/// Most rounds a vault may ask for; the header is only authenticated after deriving
+/// the key, so a forged count must not keep the reader busy.
+const MAX_ROUNDS: u32 = 10_000_000;
+
+/// What the key of a vault is derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyKind {
+    Passphrase,
+    KeyFile,
+}
+
+impl KeyKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyKind::Passphrase => "passphrase",
+            KeyKind::KeyFile => "key-file",
+        }
+    }
+}
+
+/// The secret a vault is sealed with.
+#[derive(Clone)]
+pub enum VaultKey {
+    Passphrase(String),
+    /// Contents of a key file, without surrounding whitespace.
+    KeyFile(Vec<u8>),
+}
+
+impl VaultKey {
+    pub fn key_file(path: &Path) -> Result<Self> {
+        let key = read_file(path)?;
+        let key = key.trim_ascii().to_vec();
+        if key.is_empty() {
+            return Err(Error::KeyUnavailable(format!(
+                "{} is empty",
+                path.display()
+            )));
+        }
+        Ok(VaultKey::KeyFile(key))
+    }
+
+    /// The first line of a file as the passphrase.
+    pub fn passphrase_file(path: &Path) -> Result<Self> {
+        let text = String::from_utf8_lossy(&read_file(path)?).into_owned();
+        let passphrase = text.lines().next().unwrap_or_default();
+        if passphrase.is_empty() {
+            return Err(Error::KeyUnavailable(format!(
+                "{}: empty passphrase",
+                path.display()
+            )));
+        }
+        Ok(VaultKey::Passphrase(passphrase.to_string()))
+    }
+
+    /// `$XDG_CONFIG_HOME/gcookie/vault.key`, or `gcookie/vault.key` in the platform config
+    /// directory.
+    pub fn default_key_file() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(dirs::config_dir)?;
+        Some(dir.join("gcookie").join("vault.key"))
+    }
+
+    /// The key of a vault sealed with `kind`: `GCOOKIE_VAULT_PASSPHRASE` for a passphrase;
+    /// the file named by `GCOOKIE_VAULT_KEY_FILE`, else [`VaultKey::default_key_file`],
+    /// for a key file.
+    pub fn from_env(kind: KeyKind) -> Result<Self> {
+        let var = |name| env::var_os(name).filter(|v| !v.is_empty());
+        match kind {
+            KeyKind::Passphrase => match var("GCOOKIE_VAULT_PASSPHRASE") {
+                Some(passphrase) => Ok(VaultKey::Passphrase(
+                    passphrase.to_string_lossy().into_owned(),
+                )),
+                None => Err(Error::KeyUnavailable(
+                    "the vault has a passphrase; set GCOOKIE_VAULT_PASSPHRASE".into(),
+                )),
+            },
+            KeyKind::KeyFile => match var("GCOOKIE_VAULT_KEY_FILE") {
+                Some(path) => Self::key_file(Path::new(&path)),
+                None => match Self::default_key_file().filter(|path| path.exists()) {
+                    Some(path) => Self::key_file(&path),
+                    None => Err(Error::KeyUnavailable(
+                        "the vault has a key file; set GCOOKIE_VAULT_KEY_FILE".into(),
+                    )),
+                },
+            },
+        }
+    }
+
+    pub fn kind(&self) -> KeyKind {
+        match self {
+            VaultKey::Passphrase(_) => KeyKind::Passphrase,
+            VaultKey::KeyFile(_) => KeyKind::KeyFile,
+        }
+    }
+
+    fn derive(&self, salt: &[u8], rounds: u32) -> [u8; 32] {
+        let secret = match self {
+            VaultKey::Passphrase(passphrase) => passphrase.as_bytes(),
+            VaultKey::KeyFile(key) => key,
+        };
+        let mut key = [0; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(secret, salt, rounds, &mut key);
+        key
+    }
+}
+
+/// Write a new key file of 32 random bytes in base64, readable only by its owner.
+pub fn generate_key_file(path: &Path) -> Result<()> {
+    let mut key = [0; 32];
+    OsRng.fill_bytes(&mut key);
+    write_private(
+        path,
+        format!("{}\n", STANDARD.encode(key)).as_bytes(),
+        false,
+    )
+}
+
+/// The JSON envelope of a vault file; the header fields are authenticated with the data.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    gcookie: String,
+    version: u32,
+    key: KeyKind,
+    rounds: u32,
+    salt: String,
+    created: i64,
+    /// Base64 of the nonce and the encrypted cookies.
+    data: String,
+}
+
+impl Envelope {
+    fn aad(&self) -> Vec<u8> {
+        format!(
+            "{} {} {} {} {} {}",
+            self.gcookie,
+            self.version,
+            self.key.name(),
+            self.rounds,
+            self.salt,
+            self.created
+        )
+        .into_bytes()
+    }
+}
+
+/// Encrypt cookies into the text of a vault file.
+pub fn seal_vault(cookies: &[Cookie], key: &VaultKey) -> String {
+    let mut salt = [0; 16];
+    OsRng.fill_bytes(&mut salt);
+    let rounds = match key.kind() {
+        KeyKind::Passphrase => PASSPHRASE_ROUNDS,
+        KeyKind::KeyFile => 1,
+    };
+    let plain: Vec<_> = cookies
+        .iter()
+        .map(|cookie| {
+            serde_json::json!({
+                "domain": cookie.host,
+                "path": cookie.path,
+                "name": cookie.name,
+                "value": cookie.value,
+                "expires": cookie.expires,
+                "secure": cookie.secure,
+                "httpOnly": cookie.http_only,
+                "sameSite": cookie.same_site.to_string(),
+                "creation": cookie.creation,
+                "lastAccessed": cookie.last_access,
+            })
+        })
+        .collect();
+    let mut envelope = Envelope {
+        gcookie: NAME.to_string(),
+        version: VERSION,
+        key: key.kind(),
+        rounds,
+        salt: STANDARD.encode(salt),
+        created: crate::browser::import::now(),
+        data: String::new(),
+    };
+    let sealed = seal(
+        &key.derive(&salt, rounds),
+        &envelope.aad(),
+        serde_json::Value::Array(plain).to_string().as_bytes(),
+    );
+    envelope.data = STANDARD.encode(sealed);
+    serde_json::to_string_pretty(&envelope).unwrap_or_default()
+}
+
+/// Seal cookies into a vault file readable only by its owner, replacing an existing one.
+pub fn write_vault(path: &Path, cookies: &[Cookie], key: &VaultKey) -> Result<()> {
+    write_private(path, seal_vault(cookies, key).as_bytes(), true)
+}
+
+/// What a vault file needs to be opened.
+pub fn vault_key_kind(text: &str) -> Result<KeyKind> {
+    Ok(read_envelope(text)?.key)
+}
+
+fn read_envelope(text: &str) -> Result<Envelope> {
+    let envelope: Envelope = serde_json::from_str(text)
+        .ok()
+        .filter(|envelope: &Envelope| envelope.gcookie == NAME)
+        .ok_or_else(|| Error::Parse("not a gcookie vault".into()))?;
+    if envelope.version != VERSION {
+        return Err(Error::Parse(format!(
+            "unsupported vault version {}",
+            envelope.version
+        )));
+    }
+    if !(1..=MAX_ROUNDS).contains(&envelope.rounds) {
+        return Err(Error::Parse(format!(
+            "vault rounds {} out of range",
+            envelope.rounds
+        )));
+    }
+    Ok(envelope)
+}
+
+/// Decrypt the cookies of a vault file; wrong keys and altered files fail alike.
+pub fn open_vault(text: &str, key: &VaultKey) -> Result<SiteCookie> {
+    let envelope = read_envelope(text)?;
+    if envelope.key != key.kind() {
+        return Err(Error::KeyUnavailable(format!(
+            "the vault is sealed with a {}",
+            envelope.key.name()
+        )));
+    }
+    let invalid = || Error::DecryptFailed("vault: wrong key or altered file".into());
+    let salt = STANDARD.decode(&envelope.salt).map_err(|_| invalid())?;
+    let sealed = STANDARD.decode(&envelope.data).map_err(|_| invalid())?;
+    let plain = open(
+        &key.derive(&salt, envelope.rounds),
+        &envelope.aad(),
+        &sealed,
+    )
+    .ok_or_else(invalid)?;
+    parse(
+        &String::from_utf8(plain).map_err(|_| invalid())?,
+        InputFormat::Json,
+        None,
+    )
+}
+
+/// Read a vault file with the key of [`VaultKey::from_env`].
+pub fn read_vault(path: &Path) -> Result<SiteCookie> {
+    let text = String::from_utf8(read_file(path)?)
+        .map_err(|_| Error::Parse("not a gcookie vault".into()))?;
+    open_vault(&text, &VaultKey::from_env(vault_key_kind(&text)?)?)
+}
+
+/// Read a file, a missing one being [`Error::NotFound`] and other failures, like a denied
+/// permission, an I/O error naming the file.
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(path.display().to_string()),
+        kind => Error::Io(io::Error::new(kind, format!("{}: {}", path.display(), err))),
+    })
+}
+
+/// Vault files as a [`CookieSource`]; the profile is the path of the file.
+pub struct VaultSource;
+
+struct VaultStore(PathBuf);
+
+impl CookieStore for VaultStore {
+    fn cookies(&self, query: &Query) -> Result<SiteCookie> {
+        Ok(query.filter(read_vault(&self.0)?))
+    }
+}
+
+impl CookieSource for VaultSource {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn open(&self, profile: Option<&str>) -> Result<Box<dyn CookieStore>> {
+        match profile {
+            Some(path) => Ok(Box::new(VaultStore(PathBuf::from(path)))),
+            None => Err(Error::InvalidSpec(
+                "vault: give the file as vault:/path/to/file".into(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::cookie::SameSite;
+
+    #[test]
+    fn vault_ok() {
+        let cookie = Cookie {
+            host: ".bing.com".into(),
+            path: "/".into(),
+            name: "sid".into(),
+            value: "secret".into(),
+            expires: Some(1_800_000_000),
+            secure: true,
+            http_only: true,
+            same_site: SameSite::Lax,
+            creation: Some(1_700_000_000),
+            ..Default::default()
+        };
+        let key = VaultKey::KeyFile(b"0123456789abcdef".to_vec());
+        let text = seal_vault(std::slice::from_ref(&cookie), &key);
+        assert!(!text.contains("secret"));
+        assert_eq!(vault_key_kind(&text).unwrap(), KeyKind::KeyFile);
+        assert_eq!(open_vault(&text, &key).unwrap()[..], [cookie]);
+
+        let other = VaultKey::KeyFile(b"fedcba9876543210".to_vec());
+        assert!(matches!(
+            open_vault(&text, &other),
+            Err(Error::DecryptFailed(_))
+        ));
+        let passphrase = VaultKey::Passphrase("hunter2".into());
+        assert!(matches!(
+            open_vault(&text, &passphrase),
+            Err(Error::KeyUnavailable(_))
+        ));
+        // the header is authenticated too
+        let altered = text.replace("\"rounds\": 1", "\"rounds\": 2");
+        assert!(matches!(
+            open_vault(&altered, &key),
+            Err(Error::DecryptFailed(_))
+        ));
+        let forged = text.replace("\"rounds\": 1", "\"rounds\": 4294967295");
+        assert!(matches!(open_vault(&forged, &key), Err(Error::Parse(_))));
+        assert!(matches!(open_vault("[]", &key), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn read_file_err() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("vault.key");
+        assert!(matches!(
+            VaultKey::key_file(&missing),
+            Err(Error::NotFound(_))
+        ));
+        // a directory exists but cannot be read as a file
+        assert!(matches!(VaultKey::key_file(dir.path()), Err(Error::Io(_))));
+        assert!(matches!(read_vault(dir.path()), Err(Error::Io(_))));
+        fs::write(&missing, "\n").unwrap();
+        assert!(matches!(
+            VaultKey::passphrase_file(&missing),
+            Err(Error::KeyUnavailable(_))
+        ));
+    }
+}
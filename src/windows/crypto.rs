@@ -1,4 +1,4 @@
-use aes_gcm::aead::Aead;
+use aes_gcm::aead::{Aead, AeadCore, OsRng};
 use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
 use windows::Win32::Foundation::{LocalFree, HLOCAL};
 use windows::Win32::Security::Cryptography::{CryptUnprotectData, CRYPT_INTEGER_BLOB};
@@ -26,14 +26,21 @@ pub fn crypt_unprotect_data(crypted_bytes: &[u8]) -> windows::core::Result<Vec<u
     }
 }
 
-pub fn aes_gcm_decrypt(value: &[u8], key: &[u8], nonce: &[u8]) -> Vec<u8> {
+pub fn aes_gcm_decrypt(value: &[u8], key: &[u8], nonce: &[u8]) -> aes_gcm::aead::Result<Vec<u8>> {
     let key = Key::<Aes256Gcm>::from_slice(key);
     let cipher = Aes256Gcm::new(key);
 
     let nonce = Nonce::from_slice(nonce);
 
-    let plaintext = cipher
-        .decrypt(nonce, value)
-        .expect("decryption aes_gcm value failure!");
-    plaintext
+    cipher.decrypt(nonce, value)
+}
+
+/// Encrypt with a random nonce; returns the nonce and the ciphertext.
+pub fn aes_gcm_encrypt(value: &[u8], key: &[u8]) -> aes_gcm::aead::Result<(Vec<u8>, Vec<u8>)> {
+    let key = Key::<Aes256Gcm>::from_slice(key);
+    let cipher = Aes256Gcm::new(key);
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    Ok((nonce.to_vec(), cipher.encrypt(&nonce, value)?))
 }